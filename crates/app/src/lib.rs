@@ -1,10 +1,19 @@
+// the entry points of the web page, there is nothing to build outside of the browser
+#![cfg(target_arch = "wasm32")]
+
 use std::{cell::RefCell, rc::Rc};
 
-use canvas::Canvas;
+use canvas::{Canvas, Color};
 use debug_ui::{DebugUI, Param, ParamParam};
 use engine::{RenderConfig, Simulation, SimulationRunner, SpeedConfig};
+use langton::{CellChange, CoreParams, GameConfig, StepBatcher, WorkerRequest, WorkerSim};
 use wasm_bindgen::prelude::*;
 
+/// Url tag moving the langton simulation to a web worker, e.g. `?animation=langton&worker`
+const URL_TAG_WORKER: &str = "worker";
+/// Module loading the wasm in the worker and forwarding messages to [`LangtonWorker`]
+const WORKER_SCRIPT: &str = "./worker.js";
+
 // --- Registry -----------------------------------------------------------
 
 type StartFuture = std::pin::Pin<Box<dyn std::future::Future<Output = ()>>>;
@@ -144,12 +153,16 @@ async fn start_langton() {
         ..Default::default()
     });
 
+    let export_requested = debug_ui.button("Export PNG + metadata");
     debug_ui.add_footer();
 
     let config = Rc::new(RefCell::new(game_config));
     let step_counter = Rc::new(RefCell::new(debug_ui.step_counter()));
     let debug_ui = Rc::new(RefCell::new(debug_ui));
     let needs_clear = debug_ui.borrow().needs_clear();
+    let use_worker = common::url()
+        .query_pairs()
+        .any(|(k, _)| k == URL_TAG_WORKER);
 
     loop {
         let mut canvas = Canvas::new(cell_border_size.clone(), cell_size.clone());
@@ -179,7 +192,6 @@ async fn start_langton() {
                 }
             });
 
-            let game = langton::Game::new(config.clone(), canvas.width(), canvas.height());
             let speed_config = SpeedConfig {
                 final_steps_per_frame: final_steps_per_frame.clone(),
                 speedup_frames: speedup_frames.clone(),
@@ -188,14 +200,28 @@ async fn start_langton() {
             let render_config = RenderConfig {
                 alpha_retention_factor: alpha_retention_factor.clone(),
             };
-            let runner = SimulationRunner::new(
-                game,
-                speed_config,
-                render_config,
-                needs_clear.clone(),
-                step_counter.clone(),
-            );
-            runner.run(&mut canvas, should_restart).await;
+            if use_worker {
+                let game = WorkerGame::new(config.clone(), export_requested.clone());
+                let runner = SimulationRunner::new(
+                    game,
+                    speed_config,
+                    render_config,
+                    needs_clear.clone(),
+                    step_counter.clone(),
+                );
+                runner.run(&mut canvas, should_restart).await;
+            } else {
+                let game = langton::Game::new(config.clone(), canvas.width(), canvas.height())
+                    .with_export_request(export_requested.clone());
+                let runner = SimulationRunner::new(
+                    game,
+                    speed_config,
+                    render_config,
+                    needs_clear.clone(),
+                    step_counter.clone(),
+                );
+                runner.run(&mut canvas, should_restart).await;
+            }
 
             if *stop_mode.borrow() == Some(debug_ui::RestartMode::Full) {
                 break;
@@ -204,6 +230,164 @@ async fn start_langton() {
     }
 }
 
+// --- Langton in a web worker ---------------------------------------------
+
+/// Worker side entry point, instantiated by `worker.js`
+#[wasm_bindgen]
+pub struct LangtonWorker(WorkerSim);
+
+#[wasm_bindgen]
+impl LangtonWorker {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        console_error_panic_hook::set_once();
+        Self(WorkerSim::default())
+    }
+
+    /// Handles an encoded [`WorkerRequest`], returns the encoded changed cells after a step
+    pub fn handle(&mut self, data: &[f64]) -> Option<Vec<u32>> {
+        let Some(request) = WorkerRequest::decode(data) else {
+            web_sys::console::error_1(&format!("Invalid worker request: {data:?}").into());
+            return None;
+        };
+        self.0.handle(request)
+    }
+}
+
+impl Default for LangtonWorker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Main thread side of the worker simulation: forwards the frame's steps to the worker
+/// and draws the cells it reports as changed.
+///
+/// Only one step request is in flight at a time. Steps requested and frames rendered
+/// while the worker is still busy are sent together with the next request (see
+/// [`StepBatcher`]), so a slow simulation runs at the worker's pace instead of blocking
+/// the main thread.
+struct WorkerGame {
+    worker: web_sys::Worker,
+    _on_message: Closure<dyn FnMut(web_sys::MessageEvent)>,
+    config: Rc<RefCell<GameConfig>>,
+    /// changes received from the worker, drawn on the next frame
+    received: Rc<RefCell<Vec<CellChange>>>,
+    batcher: Rc<RefCell<StepBatcher>>,
+    export_requested: Rc<RefCell<bool>>,
+    sent_params: Option<CoreParams>,
+    /// (width, height, screen_height) last sent to the worker
+    sent_size: Option<(usize, usize, usize)>,
+}
+
+impl WorkerGame {
+    fn new(config: Rc<RefCell<GameConfig>>, export_requested: Rc<RefCell<bool>>) -> Self {
+        let options = web_sys::WorkerOptions::new();
+        options.set_type(web_sys::WorkerType::Module);
+        let worker = web_sys::Worker::new_with_options(WORKER_SCRIPT, &options)
+            .expect("failed to start the simulation worker");
+
+        let received = Rc::new(RefCell::new(vec![]));
+        let batcher = Rc::new(RefCell::new(StepBatcher::default()));
+        let on_message = {
+            let received = received.clone();
+            let batcher = batcher.clone();
+            Closure::<dyn FnMut(_)>::new(move |event: web_sys::MessageEvent| {
+                if !batcher.borrow_mut().complete() {
+                    return;
+                }
+                let data: js_sys::Uint32Array = event.data().unchecked_into();
+                received
+                    .borrow_mut()
+                    .extend(langton::decode_changes(&data.to_vec()));
+            })
+        };
+        worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        Self {
+            worker,
+            _on_message: on_message,
+            config,
+            received,
+            batcher,
+            export_requested,
+            sent_params: None,
+            sent_size: None,
+        }
+    }
+
+    fn send(&self, request: WorkerRequest) {
+        let data = js_sys::Float64Array::from(request.encode().as_slice());
+        self.worker.post_message(&data).unwrap();
+    }
+}
+
+impl Drop for WorkerGame {
+    fn drop(&mut self) {
+        self.worker.terminate();
+    }
+}
+
+impl Simulation for WorkerGame {
+    fn step(&mut self, _canvas: &mut Canvas) {
+        self.batcher.borrow_mut().queue(1);
+    }
+
+    fn end_frame(&mut self, canvas: &mut Canvas) {
+        for change in self.received.borrow_mut().drain(..) {
+            canvas.fill_rect(change.x, change.y, change.color);
+        }
+        if self.export_requested.replace(false) {
+            let steps = self.batcher.borrow().steps_done();
+            canvas.flush();
+            let metadata = self.config.borrow().metadata(steps);
+            langton::download_export(langton::export_with_metadata(canvas, &metadata));
+        }
+
+        let size = (canvas.width(), canvas.height(), canvas.screen_height());
+        if self.sent_size != Some(size) {
+            self.send(WorkerRequest::Resize {
+                width: size.0,
+                height: size.1,
+                screen_height: size.2,
+            });
+            self.sent_size = Some(size);
+        }
+        let params = self.config.borrow().core_params();
+        if self.sent_params != Some(params) {
+            self.send(WorkerRequest::Configure(params));
+            self.sent_params = Some(params);
+        }
+
+        let batch = {
+            let mut batcher = self.batcher.borrow_mut();
+            batcher.end_frame();
+            batcher.take_batch()
+        };
+        if let Some(request) = batch {
+            self.send(request);
+        }
+    }
+
+    fn on_canvas_resize(&mut self, _new_width: usize, _new_height: usize) {
+        // the new size is sent along with the next step request, see `end_frame`. The
+        // changes until then are for the old board.
+        self.received.borrow_mut().clear();
+        self.batcher.borrow_mut().resize();
+    }
+
+    fn on_clear(&mut self, canvas: &mut Canvas) {
+        canvas.clear(self.bg_color());
+        self.received.borrow_mut().clear();
+        self.batcher.borrow_mut().clear();
+        self.send(WorkerRequest::Clear);
+    }
+
+    fn bg_color(&self) -> Color {
+        self.config.borrow().core_params().bg_color
+    }
+}
+
 async fn start_blinker() {
     let mut debug_ui = DebugUI::new("Blinker parameters");
 
@@ -0,0 +1,17 @@
+// Runs the langton simulation off the main thread, started with `?worker`.
+// Messages received while the wasm module loads are buffered and replayed.
+import init, { LangtonWorker } from "./pkg/app.js";
+
+const pending = [];
+self.onmessage = (event) => pending.push(event.data);
+
+await init();
+const worker = new LangtonWorker();
+const handle = (data) => {
+    const changes = worker.handle(data);
+    if (changes !== undefined) {
+        self.postMessage(changes, [changes.buffer]);
+    }
+};
+pending.forEach(handle);
+self.onmessage = (event) => handle(event.data);
@@ -1,9 +1,16 @@
-use common::get_canvas_parent;
+//! Outside of the browser (native tests, tools), [`Canvas`] draws nothing: see
+//! [`Canvas::mock`].
+
 use debug_ui::Param;
 use std::{cell::RefCell, rc::Rc};
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{console::warn_1, wasm_bindgen::prelude::*, window};
+#[cfg(target_arch = "wasm32")]
+use {
+    common::get_canvas_parent,
+    wasm_bindgen_futures::JsFuture,
+    web_sys::{console::warn_1, wasm_bindgen::prelude::*, window},
+};
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(inline_js = "
 export function batch_fill_rects(ctx, data) {
     const len = data.length;
@@ -27,8 +34,13 @@ extern "C" {
     fn batch_fill_rects(ctx: &web_sys::CanvasRenderingContext2d, data: &js_sys::Uint16Array);
 }
 
+/// Grid of `width` x `height` cells. Flat buffers of cells (here and in the
+/// simulations) are column-major: the cell `(x, y)` is at index `x * height + y`,
+/// see [`Canvas::xy_to_index`].
 pub struct Canvas {
+    #[cfg(target_arch = "wasm32")]
     element: web_sys::HtmlCanvasElement,
+    #[cfg(target_arch = "wasm32")]
     context: web_sys::CanvasRenderingContext2d,
     /// render calls queue
     queue: Vec<DrawCall>,
@@ -40,6 +52,7 @@ pub struct Canvas {
     /// in pixels
     cell_size: Rc<RefCell<debug_ui::Param<usize>>>,
     /// in pixels
+    #[cfg(target_arch = "wasm32")]
     cell_border_size: Rc<RefCell<debug_ui::Param<usize>>>,
     /// in cells
     width: usize,
@@ -55,9 +68,22 @@ pub struct Canvas {
     canvas_height: usize,
     last_cell_size: usize,
     /// Persistent buffer for flush, reused across frames to avoid per-frame allocation
+    #[cfg(target_arch = "wasm32")]
     flush_buf: Vec<u16>,
+    /// multiplier applied to `cell_size`
+    zoom: f64,
+    /// snap `zoom` to whole numbers, see [`Canvas::with_integer_zoom`]
+    integer_zoom: bool,
+    /// css filter applied to everything drawn, see [`Canvas::with_filter`]
+    #[cfg(target_arch = "wasm32")]
+    filter: Option<String>,
+    /// the fade pass of `fill_canvas` only runs once every `fade_interval` calls
+    fade_interval: u32,
+    /// number of `fill_canvas` calls so far, used to pace the fade
+    fade_frame: u64,
 }
 
+#[cfg(target_arch = "wasm32")]
 impl Drop for Canvas {
     fn drop(&mut self) {
         self.element.remove();
@@ -86,6 +112,7 @@ impl Color {
         }
     }
 
+    #[cfg(any(target_arch = "wasm32", test))]
     fn invert(self) -> Self {
         match self {
             Color::Rgb { r, g, b } => Color::Rgb {
@@ -105,6 +132,7 @@ impl Color {
     }
 }
 
+#[cfg(target_arch = "wasm32")]
 fn color_components(color: Color) -> (u8, u8, u8, u8) {
     match color {
         Color::Rgb { r, g, b } => (r, g, b, 255),
@@ -114,6 +142,87 @@ fn color_components(color: Color) -> (u8, u8, u8, u8) {
     }
 }
 
+#[cfg(any(target_arch = "wasm32", test))]
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// CRC-32 (ISO-HDLC) as used by png chunks
+#[cfg(any(target_arch = "wasm32", test))]
+fn crc32(data: impl IntoIterator<Item = u8>) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Insert a `tEXt` chunk for each `(keyword, text)` right before the `IEND` chunk.
+/// Malformed pngs are returned unchanged.
+#[cfg(any(target_arch = "wasm32", test))]
+fn png_with_text(png: &[u8], entries: &[(&str, &str)]) -> Vec<u8> {
+    if !png.starts_with(PNG_SIGNATURE) {
+        return png.to_vec();
+    }
+    let mut offset = PNG_SIGNATURE.len();
+    let iend = loop {
+        let Some(header) = png.get(offset..offset + 8) else {
+            return png.to_vec();
+        };
+        let len = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+        if &header[4..8] == b"IEND" {
+            break offset;
+        }
+        // length + type + data + crc
+        offset += 12 + len;
+    };
+
+    let mut out = Vec::with_capacity(png.len() + entries.len() * 64);
+    out.extend_from_slice(&png[..iend]);
+    for (keyword, text) in entries {
+        let data: Vec<u8> = keyword
+            .bytes()
+            .chain(std::iter::once(0))
+            .chain(text.bytes())
+            .collect();
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        out.extend_from_slice(b"tEXt");
+        out.extend_from_slice(&data);
+        let crc = crc32(b"tEXt".iter().chain(&data).copied());
+        out.extend_from_slice(&crc.to_be_bytes());
+    }
+    out.extend_from_slice(&png[iend..]);
+    out
+}
+
+/// Largest cell the batched draw calls can carry, their sizes are `u16`
+const MAX_CELL_SIZE: usize = u16::MAX as usize;
+
+fn zoomed_cell_size(base: usize, zoom: f64, integer_zoom: bool) -> usize {
+    let size = if integer_zoom {
+        base.saturating_mul(zoom.round().max(1.0) as usize)
+    } else {
+        (base as f64 * zoom).round().max(1.0) as usize
+    };
+    size.min(MAX_CELL_SIZE)
+}
+
+/// Flat index of the cell `(x, y)` in a grid `height` cells high: `x * height + y`
+fn xy_to_index(x: usize, y: usize, width: usize, height: usize) -> Option<usize> {
+    (x < width && y < height).then(|| x * height + y)
+}
+
+/// Inverse of [`xy_to_index`]
+fn index_to_xy(index: usize, width: usize, height: usize) -> Option<(usize, usize)> {
+    (index < width * height).then(|| (index / height, index % height))
+}
+
+fn is_fade_frame(frame: u64, interval: u32) -> bool {
+    frame.is_multiple_of(u64::from(interval.max(1)))
+}
+
 /// queued rectangle draw call
 #[derive(Clone)]
 struct DrawCall {
@@ -123,22 +232,28 @@ struct DrawCall {
 }
 
 impl Canvas {
+    #[cfg(target_arch = "wasm32")]
     pub fn new(
         cell_border_size: Rc<RefCell<Param<usize>>>,
         cell_size: Rc<RefCell<Param<usize>>>,
     ) -> Self {
-        let Some(canvas) = Self::create_canvas() else {
-            panic!("Failed to get canvas!")
-        };
-        let Some(context) = Self::get_context(&canvas) else {
-            panic!("Failed to get context 2d out of canvas!")
-        };
+        Self::create(cell_border_size, cell_size).expect("Failed to get canvas or its 2d context!")
+    }
+
+    /// Create a canvas in the page, `None` when there is no page to put it in
+    #[cfg(target_arch = "wasm32")]
+    pub fn create(
+        cell_border_size: Rc<RefCell<Param<usize>>>,
+        cell_size: Rc<RefCell<Param<usize>>>,
+    ) -> Option<Self> {
+        let canvas = Self::create_canvas()?;
+        let context = Self::get_context(&canvas)?;
 
         let base_screen_height =
             window().unwrap().inner_height().unwrap().as_f64().unwrap() as usize;
         let base_screen_height = std::cmp::min(canvas.height() as usize, base_screen_height);
 
-        Self {
+        Some(Self {
             element: canvas.clone(),
             context,
             cell_size,
@@ -155,9 +270,51 @@ impl Canvas {
             screen_height: 0,
             last_cell_size: 0,
             flush_buf: vec![],
-        }
+            zoom: 1.0,
+            integer_zoom: false,
+            filter: None,
+            fade_interval: 1,
+            fade_frame: 0,
+        })
+    }
+
+    /// There is no page to put a canvas in outside of the browser, see [`Canvas::mock`]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn create(
+        _cell_border_size: Rc<RefCell<Param<usize>>>,
+        _cell_size: Rc<RefCell<Param<usize>>>,
+    ) -> Option<Self> {
+        None
+    }
+
+    /// Canvas of `width` x `height` cells that draws nothing, for native tests.
+    /// [`Canvas::flush`] still optimises the draw queue and records the cells, see
+    /// [`Canvas::flushed_cell`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn mock(width: usize, height: usize) -> Self {
+        let mut canvas = Self {
+            cell_size: Rc::new(RefCell::new(Param::fixed(1))),
+            canvas_width: width,
+            canvas_height: height,
+            base_screen_height: height,
+            queue: vec![],
+            dedup_vec: vec![],
+            dedup_dirty: vec![],
+            last_frame: vec![],
+            width: 0,
+            height: 0,
+            screen_height: 0,
+            last_cell_size: 0,
+            zoom: 1.0,
+            integer_zoom: false,
+            fade_interval: 1,
+            fade_frame: 0,
+        };
+        canvas.calculate_size();
+        canvas
     }
 
+    #[cfg(target_arch = "wasm32")]
     pub fn new_with_element(
         element: web_sys::HtmlCanvasElement,
         cell_border_size: Rc<RefCell<Param<usize>>>,
@@ -183,13 +340,115 @@ impl Canvas {
             screen_height: 0,
             last_cell_size: 0,
             flush_buf: vec![],
+            zoom: 1.0,
+            integer_zoom: false,
+            filter: None,
+            fade_interval: 1,
+            fade_frame: 0,
         }
     }
 
+    /// Run the trail fade of [`Canvas::fill_canvas`] only once every `frames` frames
+    /// (`0` is treated as `1`, the default: fade on every frame).
+    ///
+    /// Interval and alpha retention multiply: with a retention of `r` (out of 255) and an
+    /// interval of `n`, a cell keeps `(r / 255)^(1 / n)` of its alpha per frame on average,
+    /// so trails persist `n` times longer. A long interval with a low retention gives
+    /// visible steps in the decay, a short one with a high retention gives a smooth fade.
+    pub fn with_fade_interval(mut self, frames: u32) -> Self {
+        self.fade_interval = frames.max(1);
+        self
+    }
+
+    /// Apply a css filter (`"grayscale(1)"`, `"sepia(0.8)"`, `"invert(1)"`,
+    /// `"hue-rotate(90deg)"`, ...) to the cells, the fade and the clears alike.
+    /// An empty string or `"none"` disables it, which is the default.
+    ///
+    /// Browsers without canvas filters only get a warning and draw unfiltered.
+    #[cfg(target_arch = "wasm32")]
+    pub fn with_filter(mut self, filter: &str) -> Self {
+        let filter = filter.trim();
+        if filter.is_empty() || filter == "none" {
+            self.filter = None;
+        } else if js_sys::Reflect::has(&self.context, &"filter".into()).unwrap_or(false) {
+            self.filter = Some(filter.to_owned());
+        } else {
+            warn_1(
+                &format!("[LANGTON][CANVAS] canvas filters are not supported by this browser, ignoring filter {filter:?}").into(),
+            );
+        }
+        self
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_filter(self, _filter: &str) -> Self {
+        self
+    }
+
+    /// (Re)apply the filter, in case the context state got reset since the last draw
+    #[cfg(target_arch = "wasm32")]
+    fn apply_filter(&self) {
+        if let Some(filter) = &self.filter {
+            self.context.set_filter(filter);
+        }
+    }
+
+    /// Only zoom by whole multiples (2x, 3x, ...) of the `cell_size` param.
+    ///
+    /// A fractional zoom rounds the zoomed cell to the nearest pixel, so the cell grid
+    /// drifts out of proportion with the unzoomed one (a 3px cell at 1.5x becomes 5px).
+    /// With integer zoom every cell stays an exact block of `zoom * cell_size` pixels,
+    /// which keeps pixel-art output crisp.
+    pub fn with_integer_zoom(mut self, integer_zoom: bool) -> Self {
+        self.integer_zoom = integer_zoom;
+        self
+    }
+
+    /// Scale the cells by `zoom`. The grid is recomputed on the next frame.
+    /// A zoom that isn't a finite positive number is ignored.
+    pub fn set_zoom(&mut self, zoom: f64) {
+        if zoom.is_finite() && zoom > 0.0 {
+            self.zoom = zoom;
+        }
+    }
+
+    pub fn zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    /// Size of a cell on screen, in pixels
+    pub fn cell_size(&self) -> usize {
+        zoomed_cell_size(self.cell_size.borrow().get(), self.zoom, self.integer_zoom)
+    }
+
     pub fn fill_rect(&mut self, x: usize, y: usize, color: Color) {
         self.queue.push(DrawCall { x, y, color });
     }
 
+    /// Fill the cell at flat index `index` (`x * height + y`). Indices outside the grid
+    /// are ignored.
+    pub fn fill_index(&mut self, index: usize, color: Color) {
+        if let Some((x, y)) = self.index_to_xy(index) {
+            self.fill_rect(x, y, color);
+        }
+    }
+
+    /// Flat index `x * height + y` of the cell `(x, y)`, `None` outside the grid
+    pub fn xy_to_index(&self, x: usize, y: usize) -> Option<usize> {
+        xy_to_index(x, y, self.width, self.height)
+    }
+
+    /// Cell `(x, y)` at flat index `index`, `None` outside the grid
+    pub fn index_to_xy(&self, index: usize) -> Option<(usize, usize)> {
+        index_to_xy(index, self.width, self.height)
+    }
+
+    /// Color of the cell `(x, y)` as of the last [`Canvas::flush`], `None` if nothing was
+    /// drawn there since the last clear
+    pub fn flushed_cell(&self, x: usize, y: usize) -> Option<Color> {
+        *self.last_frame.get(x)?.get(y)?
+    }
+
     pub fn width(&self) -> usize {
         self.width
     }
@@ -203,7 +462,7 @@ impl Canvas {
     }
 
     fn calculate_size(&mut self) {
-        let cell_size = self.cell_size.borrow().get();
+        let cell_size = self.cell_size();
         self.last_cell_size = cell_size;
         self.width = (self.canvas_width as f64 / cell_size as f64).ceil() as usize;
         self.height = (self.canvas_height as f64 / cell_size as f64).ceil() as usize;
@@ -216,7 +475,7 @@ impl Canvas {
     }
 
     fn calculate_size_if_needed(&mut self) {
-        if self.cell_size.borrow().get() != self.last_cell_size {
+        if self.cell_size() != self.last_cell_size {
             self.calculate_size();
             assert!(self.width > 0);
             assert!(self.height > 0);
@@ -224,6 +483,7 @@ impl Canvas {
     }
 
     /// animation: function that renders a single frame and returns true if it is done
+    #[cfg(target_arch = "wasm32")]
     pub async fn play_animation(&mut self, mut animation: impl FnMut(&mut Canvas) -> bool) {
         loop {
             // Wait for next animation frame
@@ -245,14 +505,21 @@ impl Canvas {
         }
     }
 
+    /// Without a browser to pace them, the frames run back to back
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn play_animation(&mut self, mut animation: impl FnMut(&mut Canvas) -> bool) {
+        loop {
+            self.calculate_size_if_needed();
+            let done = animation(self);
+            self.flush();
+            if done {
+                break;
+            }
+        }
+    }
+
     pub fn clear(&mut self, color: Color) {
-        self.context.set_fill_style_str(&color.to_css_color());
-        self.context.fill_rect(
-            0.0,
-            0.0,
-            self.canvas_width as f64,
-            self.canvas_height as f64,
-        );
+        self.fill_all(color);
         // Reset last_frame so subsequent draws won't be skipped by dedup
         for col in &mut self.last_frame {
             for cell in col.iter_mut() {
@@ -263,6 +530,32 @@ impl Canvas {
     }
 
     pub fn fill_canvas(&mut self, retention_factor: u8, bg_color: Option<Color>) {
+        let frame = self.fade_frame;
+        self.fade_frame += 1;
+        if is_fade_frame(frame, self.fade_interval) {
+            self.fade(retention_factor, bg_color);
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn fill_all(&self, color: Color) {
+        self.apply_filter();
+        self.context.set_fill_style_str(&color.to_css_color());
+        self.context.fill_rect(
+            0.0,
+            0.0,
+            self.canvas_width as f64,
+            self.canvas_height as f64,
+        );
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn fill_all(&self, _color: Color) {}
+
+    #[cfg(target_arch = "wasm32")]
+    fn fade(&self, retention_factor: u8, bg_color: Option<Color>) {
+        self.apply_filter();
+
         // 1. Get and store the current globalCompositeOperation.
         let original_gco = self
             .context
@@ -309,13 +602,15 @@ impl Canvas {
         let _ = self.context.set_global_composite_operation(&original_gco);
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    fn fade(&self, _retention_factor: u8, _bg_color: Option<Color>) {}
+
     fn optimise_queue(&mut self) {
         // 1. remove dupplicate draw calls to the same cell on the same frame
         for draw in &self.queue {
-            if draw.x >= self.width || draw.y >= self.height {
+            let Some(idx) = xy_to_index(draw.x, draw.y, self.width, self.height) else {
                 continue;
-            }
-            let idx = draw.x * self.height + draw.y;
+            };
             if self.dedup_vec[idx].is_none() {
                 self.dedup_dirty.push(idx);
             }
@@ -324,11 +619,8 @@ impl Canvas {
         self.queue.clear();
         for &idx in &self.dedup_dirty {
             let color = self.dedup_vec[idx].take().unwrap();
-            self.queue.push(DrawCall {
-                x: idx / self.height,
-                y: idx % self.height,
-                color,
-            });
+            let (x, y) = index_to_xy(idx, self.width, self.height).unwrap();
+            self.queue.push(DrawCall { x, y, color });
         }
         self.dedup_dirty.clear();
 
@@ -348,8 +640,17 @@ impl Canvas {
         if self.queue.is_empty() {
             return;
         }
+        self.draw_queue();
+        for draw in &self.queue {
+            self.last_frame[draw.x][draw.y] = Some(draw.color);
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn draw_queue(&mut self) {
+        self.apply_filter();
 
-        let cell_size = self.cell_size.borrow().get();
+        let cell_size = self.cell_size();
         let border_size = self.cell_border_size.borrow().get();
         let border_size = if cell_size <= 2 * border_size {
             0
@@ -363,14 +664,11 @@ impl Canvas {
 
             for draw_call in &self.queue {
                 let DrawCall { x, y, color } = draw_call;
-                let cs = cell_size as u8;
+                let cs = cell_size as u16;
                 let ix = (*x * cell_size) as u16;
                 let iy = (*y * cell_size) as u16;
                 let (r, g, b, a) = color_components(*color);
-                buf.extend_from_slice(&[
-                    ix, iy, cs as u16, cs as u16, r as u16, g as u16, b as u16, a as u16,
-                ]);
-                self.last_frame[*x][*y] = Some(*color);
+                buf.extend_from_slice(&[ix, iy, cs, cs, r as u16, g as u16, b as u16, a as u16]);
             }
 
             let js_array = js_sys::Uint16Array::from(buf.as_slice());
@@ -382,19 +680,17 @@ impl Canvas {
 
             for draw_call in &self.queue {
                 let DrawCall { x, y, color } = draw_call;
-                let cs = cell_size as u8;
+                let cs = cell_size as u16;
                 let ix = (*x * cell_size) as u16;
                 let iy = (*y * cell_size) as u16;
 
                 let inv = color.invert();
                 let (r, g, b, a) = color_components(inv);
-                buf.extend_from_slice(&[
-                    ix, iy, cs as u16, cs as u16, r as u16, g as u16, b as u16, a as u16,
-                ]);
+                buf.extend_from_slice(&[ix, iy, cs, cs, r as u16, g as u16, b as u16, a as u16]);
 
                 let (r, g, b, a) = color_components(*color);
-                let bs = border_size as u8;
-                let inner_size = (cs - 2 * bs) as u16;
+                let bs = border_size as u16;
+                let inner_size = cs - 2 * bs;
                 buf.extend_from_slice(&[
                     ix + border_size as u16,
                     iy + border_size as u16,
@@ -405,7 +701,6 @@ impl Canvas {
                     b as u16,
                     a as u16,
                 ]);
-                self.last_frame[*x][*y] = Some(*color);
             }
 
             let js_array = js_sys::Uint16Array::from(buf.as_slice());
@@ -413,6 +708,65 @@ impl Canvas {
         }
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    fn draw_queue(&mut self) {}
+
+    /// Draw what has been flushed on `other` onto this canvas, its top left corner at `at`
+    /// and its size multiplied by `scale`. Draws immediately, outside of the draw queue.
+    ///
+    /// `at` and `scale` are in backing store pixels (`canvas.width`), not css pixels. Both
+    /// canvases are sized 1:1 with css pixels here, so on a high DPI screen the browser
+    /// upscales the result. A canvas whose backing store is `k` times denser than this one
+    /// should be drawn with `scale / k` to appear at the same size. Image smoothing is
+    /// turned off during the copy so cells stay crisp when scaled up.
+    ///
+    /// The copied pixels are not tracked by the dedup of [`Canvas::flush`]: a later draw
+    /// call of the same color as the previous one on a covered cell will be skipped.
+    #[cfg(target_arch = "wasm32")]
+    pub fn blit_from(&mut self, other: &Canvas, at: (f64, f64), scale: f64) {
+        let smoothing = self.context.image_smoothing_enabled();
+        self.context.set_image_smoothing_enabled(false);
+        self.context
+            .draw_image_with_html_canvas_element_and_dw_and_dh(
+                &other.element,
+                at.0,
+                at.1,
+                other.canvas_width as f64 * scale,
+                other.canvas_height as f64 * scale,
+            )
+            .unwrap();
+        self.context.set_image_smoothing_enabled(smoothing);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn blit_from(&mut self, _other: &Canvas, _at: (f64, f64), _scale: f64) {}
+
+    /// Current content of the canvas as PNG, with each `(keyword, text)` of `metadata`
+    /// embedded as a `tEXt` chunk. Queued draw calls that weren't flushed yet are not included.
+    #[cfg(target_arch = "wasm32")]
+    pub fn export_png(&self, metadata: &[(&str, &str)]) -> web_sys::Blob {
+        let data_url = self.element.to_data_url_with_type("image/png").unwrap();
+        let base64 = data_url
+            .strip_prefix("data:image/png;base64,")
+            .expect("canvas should export as a base64 png data url");
+        // atob returns a "binary string": one char per byte
+        let png: Vec<u8> = window()
+            .unwrap()
+            .atob(base64)
+            .unwrap()
+            .chars()
+            .map(|c| c as u8)
+            .collect();
+        let png = png_with_text(&png, metadata);
+
+        let parts = js_sys::Array::new();
+        parts.push(&js_sys::Uint8Array::from(png.as_slice()));
+        let options = web_sys::BlobPropertyBag::new();
+        options.set_type("image/png");
+        web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options).unwrap()
+    }
+
+    #[cfg(target_arch = "wasm32")]
     fn create_canvas() -> Option<web_sys::HtmlCanvasElement> {
         let document = web_sys::window()?.document()?;
         let body = document.body().unwrap();
@@ -442,6 +796,7 @@ impl Canvas {
         Some(canvas)
     }
 
+    #[cfg(target_arch = "wasm32")]
     fn get_context(
         canvas: &web_sys::HtmlCanvasElement,
     ) -> Option<web_sys::CanvasRenderingContext2d> {
@@ -455,7 +810,10 @@ impl Canvas {
 
 #[cfg(test)]
 mod tests {
-    use super::{Color, NamedColor};
+    use super::{
+        Color, NamedColor, PNG_SIGNATURE, crc32, index_to_xy, is_fade_frame, png_with_text,
+        xy_to_index, zoomed_cell_size,
+    };
     use rstest::rstest;
 
     #[rstest]
@@ -481,4 +839,207 @@ mod tests {
     fn test_color_invert(#[case] original: Color, #[case] expected_inverted: Color) {
         assert_eq!(original.invert(), expected_inverted);
     }
+
+    #[rstest]
+    #[case(1, &[true, true, true, true, true, true])]
+    #[case(0, &[true, true, true, true, true, true])]
+    #[case(2, &[true, false, true, false, true, false])]
+    #[case(3, &[true, false, false, true, false, false])]
+    fn test_is_fade_frame(#[case] interval: u32, #[case] expected: &[bool]) {
+        let got: Vec<bool> = (0..expected.len() as u64)
+            .map(|frame| is_fade_frame(frame, interval))
+            .collect();
+        assert_eq!(got, expected);
+    }
+
+    #[rstest]
+    #[case(b"", 0)]
+    #[case(b"IEND", 0xAE42_6082)]
+    #[case(b"123456789", 0xCBF4_3926)]
+    fn test_crc32(#[case] data: &[u8], #[case] expected: u32) {
+        assert_eq!(crc32(data.iter().copied()), expected);
+    }
+
+    fn chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut out = (data.len() as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(data);
+        let crc = crc32(kind.iter().chain(data).copied());
+        out.extend_from_slice(&crc.to_be_bytes());
+        out
+    }
+
+    #[test]
+    fn test_png_with_text_inserts_before_iend() {
+        let ihdr = chunk(b"IHDR", &[0; 13]);
+        let iend = chunk(b"IEND", &[]);
+        let png = [PNG_SIGNATURE, &ihdr, &iend].concat();
+
+        let out = png_with_text(&png, &[("seed", "42")]);
+
+        let text = chunk(b"tEXt", b"seed\x0042");
+        assert_eq!(out, [PNG_SIGNATURE, &ihdr, &text, &iend].concat());
+    }
+
+    #[test]
+    fn test_png_with_text_malformed_is_unchanged() {
+        let not_png = b"definitely not a png".to_vec();
+        assert_eq!(png_with_text(&not_png, &[("a", "b")]), not_png);
+        let truncated = [PNG_SIGNATURE, &chunk(b"IHDR", &[0; 13])].concat();
+        assert_eq!(png_with_text(&truncated, &[("a", "b")]), truncated);
+    }
+
+    #[rstest]
+    #[case(3, 1.0, false, 3)]
+    #[case(3, 1.5, false, 5)]
+    #[case(3, 1.5, true, 6)]
+    #[case(3, 2.4, true, 6)]
+    #[case(3, 2.6, true, 9)]
+    #[case(4, 0.1, false, 1)]
+    #[case(4, 0.1, true, 4)]
+    #[case(1, 3.0, true, 3)]
+    #[case(32, 8.0, false, 256)]
+    #[case(32, 8.0, true, 256)]
+    #[case(300, 1.0, false, 300)]
+    #[case(40_000, 2.0, false, 65_535)]
+    #[case(40_000, 2.0, true, 65_535)]
+    #[case(3, f64::INFINITY, true, 65_535)]
+    #[case(3, f64::INFINITY, false, 65_535)]
+    fn test_zoomed_cell_size(
+        #[case] base: usize,
+        #[case] zoom: f64,
+        #[case] integer_zoom: bool,
+        #[case] expected: usize,
+    ) {
+        assert_eq!(zoomed_cell_size(base, zoom, integer_zoom), expected);
+    }
+
+    #[rstest]
+    #[case((0, 0), Some(0))]
+    #[case((0, 2), Some(2))]
+    #[case((1, 0), Some(3))]
+    #[case((3, 2), Some(11))]
+    #[case((4, 0), None)]
+    #[case((0, 3), None)]
+    fn test_xy_to_index(#[case] (x, y): (usize, usize), #[case] expected: Option<usize>) {
+        assert_eq!(xy_to_index(x, y, 4, 3), expected);
+    }
+
+    #[rstest]
+    #[case(0, Some((0, 0)))]
+    #[case(4, Some((1, 1)))]
+    #[case(5, Some((1, 2)))]
+    #[case(11, Some((3, 2)))]
+    #[case(12, None)]
+    fn test_index_to_xy(#[case] index: usize, #[case] expected: Option<(usize, usize)>) {
+        assert_eq!(index_to_xy(index, 4, 3), expected);
+    }
+
+    #[test]
+    fn test_index_roundtrip() {
+        for index in 0..4 * 3 {
+            let (x, y) = index_to_xy(index, 4, 3).unwrap();
+            assert_eq!(xy_to_index(x, y, 4, 3), Some(index));
+        }
+    }
+
+    #[test]
+    fn test_index_empty_grid() {
+        assert_eq!(index_to_xy(0, 0, 0), None);
+        assert_eq!(xy_to_index(0, 0, 0, 0), None);
+    }
+
+    /// the mock backend, only available natively
+    #[cfg(not(target_arch = "wasm32"))]
+    mod mock {
+        use crate::{Canvas, Color};
+        use debug_ui::Param;
+        use std::{cell::RefCell, rc::Rc};
+
+        const RED: Color = Color::Rgb { r: 255, g: 0, b: 0 };
+        const BLUE: Color = Color::Rgb { r: 0, g: 0, b: 255 };
+
+        fn queued(canvas: &Canvas) -> Vec<(usize, usize, Color)> {
+            canvas
+                .queue
+                .iter()
+                .map(|draw| (draw.x, draw.y, draw.color))
+                .collect()
+        }
+
+        #[test]
+        fn test_optimise_queue_keeps_last_draw_per_cell() {
+            let mut canvas = Canvas::mock(4, 3);
+            canvas.fill_rect(1, 1, RED);
+            canvas.fill_rect(2, 0, RED);
+            canvas.fill_rect(1, 1, BLUE);
+            canvas.optimise_queue();
+            assert_eq!(queued(&canvas), vec![(1, 1, BLUE), (2, 0, RED)]);
+        }
+
+        #[test]
+        fn test_optimise_queue_drops_out_of_bounds() {
+            let mut canvas = Canvas::mock(4, 3);
+            canvas.fill_rect(4, 0, RED);
+            canvas.fill_rect(0, 3, RED);
+            canvas.fill_rect(3, 2, RED);
+            canvas.optimise_queue();
+            assert_eq!(queued(&canvas), vec![(3, 2, RED)]);
+        }
+
+        #[test]
+        fn test_optimise_queue_sorts_by_color() {
+            let mut canvas = Canvas::mock(4, 3);
+            canvas.fill_rect(0, 0, RED);
+            canvas.fill_rect(1, 0, BLUE);
+            canvas.fill_rect(2, 0, RED);
+            canvas.optimise_queue();
+            let colors: Vec<Color> = queued(&canvas).into_iter().map(|(_, _, c)| c).collect();
+            assert!(colors.is_sorted());
+        }
+
+        #[test]
+        fn test_flush_skips_unchanged_cells() {
+            let mut canvas = Canvas::mock(4, 3);
+            canvas.fill_rect(0, 0, RED);
+            canvas.flush();
+            assert_eq!(canvas.flushed_cell(0, 0), Some(RED));
+
+            canvas.fill_rect(0, 0, RED);
+            canvas.fill_rect(1, 0, BLUE);
+            canvas.optimise_queue();
+            assert_eq!(queued(&canvas), vec![(1, 0, BLUE)]);
+        }
+
+        #[test]
+        fn test_clear_forgets_flushed_cells() {
+            let mut canvas = Canvas::mock(4, 3);
+            canvas.fill_index(4, RED);
+            canvas.flush();
+            assert_eq!(canvas.flushed_cell(1, 1), Some(RED));
+
+            canvas.clear(BLUE);
+            assert_eq!(canvas.flushed_cell(1, 1), None);
+            canvas.fill_rect(1, 1, RED);
+            canvas.optimise_queue();
+            assert_eq!(queued(&canvas), vec![(1, 1, RED)]);
+        }
+
+        #[test]
+        fn test_set_zoom_ignores_invalid_values() {
+            let mut canvas = Canvas::mock(4, 3);
+            canvas.set_zoom(2.0);
+            for zoom in [0.0, -1.0, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+                canvas.set_zoom(zoom);
+                assert_eq!(canvas.zoom(), 2.0);
+            }
+            assert_eq!(canvas.cell_size(), 2);
+        }
+
+        #[test]
+        fn test_create_without_browser() {
+            let param = |value| Rc::new(RefCell::new(Param::fixed(value)));
+            assert!(Canvas::create(param(0), param(1)).is_none());
+        }
+    }
 }
@@ -32,6 +32,24 @@ pub fn url() -> url::Url {
     document().url().unwrap().parse().unwrap()
 }
 
+/// Makes the browser save `blob` as `file_name`
+pub fn download_blob(blob: &web_sys::Blob, file_name: &str) {
+    let url = web_sys::Url::create_object_url_with_blob(blob).unwrap();
+    let a = document()
+        .create_element("a")
+        .unwrap()
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .unwrap();
+    a.set_href(&url);
+    a.set_download(file_name);
+    a.click();
+
+    gloo::timers::callback::Timeout::new(1000, move || {
+        let _ = web_sys::Url::revoke_object_url(&url);
+    })
+    .forget();
+}
+
 #[macro_export]
 macro_rules! console_log {
     // Note that this is using the `log` function imported above during
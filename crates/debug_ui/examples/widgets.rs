@@ -0,0 +1,100 @@
+//! One of every debug UI widget, each logging its new value to the console when it changes.
+//!
+//! Doubles as a smoke test of the widget APIs: it has to compile against all of them.
+
+use debug_ui::{DebugColor, DebugUI, Param, ParamParam, Scale, log};
+use gloo::timers::callback::Interval;
+
+const PRESETS: &[(&str, &str)] = &[
+    ("Small and slow", "integer_slider=2&float_slider=0.1"),
+    (
+        "Big and fast",
+        "integer_slider=40&logarithmic_slider=900000",
+    ),
+];
+
+/// Logs `param` whenever its value differs from the last one seen
+fn watch<T: Copy + PartialEq + std::fmt::Debug + 'static>(name: &'static str, param: &Param<T>) {
+    let param = param.clone();
+    let mut last = param.get();
+    Interval::new(100, move || {
+        let value = param.get();
+        if value != last {
+            log!("{name} changed: {last:?} -> {value:?}");
+            last = value;
+        }
+    })
+    .forget();
+}
+
+fn main() {
+    let mut debug_ui = DebugUI::new("Every widget");
+    debug_ui.presets(PRESETS);
+
+    debug_ui.start_section("Sliders");
+    let integer_slider = debug_ui.param(ParamParam {
+        name: "integer slider",
+        default_value: 10usize,
+        range: 1..=50,
+        ..Default::default()
+    });
+    let float_slider = debug_ui.param(ParamParam {
+        name: "float slider",
+        default_value: 0.5f32,
+        range: 0.0..=1.0,
+        step_size: 0.01,
+        ..Default::default()
+    });
+    let logarithmic_slider = debug_ui.param(ParamParam {
+        name: "logarithmic slider",
+        default_value: 100.0f64,
+        range: 0.0..=1_000_000.0,
+        scale: Scale::Logarithmic,
+        ..Default::default()
+    });
+    let restart_slider = debug_ui.param(ParamParam {
+        name: "restart slider",
+        default_value: 3u32,
+        range: 0..=10,
+        needs_restart: true,
+        ..Default::default()
+    });
+
+    debug_ui.start_section("Other widgets");
+    let color = debug_ui.color_param(
+        "color",
+        DebugColor {
+            r: 30,
+            g: 30,
+            b: 30,
+        },
+    );
+    let toggle = debug_ui.param_bool("toggle", true);
+    let mut step_counter = debug_ui.step_counter();
+    debug_ui.link("A link", "https://codeberg.org/eldolfin/langton.wasm");
+    let button = debug_ui.button("A button");
+    debug_ui.add_footer();
+
+    watch("integer slider", &integer_slider);
+    watch("float slider", &float_slider);
+    watch("logarithmic slider", &logarithmic_slider);
+    watch("restart slider", &restart_slider);
+    watch("color", &color);
+    watch("toggle", &toggle);
+
+    let needs_clear = debug_ui.needs_clear();
+    Interval::new(100, move || {
+        step_counter.add_steps(1);
+        if debug_ui.take_restart_mode().is_some() {
+            log!("restart requested");
+            step_counter.reset();
+        }
+        if needs_clear.replace(false) {
+            log!("clear requested");
+        }
+        if button.replace(false) {
+            log!("button clicked");
+        }
+    })
+    .forget();
+}
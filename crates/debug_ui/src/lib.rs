@@ -1,26 +1,41 @@
-use common::document;
-use common::window;
-use gloo::events::EventListener;
-use js_sys::Date;
+//! Outside of the browser (native tests, tools), [`DebugUI`] is a stub: params keep
+//! their default value and nothing is displayed.
+
 use num_traits::{FromPrimitive, Num, ToPrimitive};
+#[cfg(any(target_arch = "wasm32", test))]
 use std::collections::HashMap;
 use std::{
     cell::RefCell,
+    fmt,
     ops::RangeInclusive,
     rc::Rc,
     str::FromStr,
-    sync::{Arc, RwLock},
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
 };
 pub use web_sys;
-use web_sys::{
-    Blob, BlobEvent, BlobPropertyBag, Document, Element, HtmlAnchorElement, HtmlInputElement,
-    KeyboardEvent, MediaRecorder, MediaRecorderOptions, Url, wasm_bindgen::JsCast as _,
+use web_sys::Element;
+#[cfg(target_arch = "wasm32")]
+use {
+    common::{document, download_blob, window},
+    gloo::events::EventListener,
+    js_sys::Date,
+    web_sys::{
+        Blob, BlobEvent, BlobPropertyBag, Document, HtmlInputElement, KeyboardEvent, MediaRecorder,
+        MediaRecorderOptions, wasm_bindgen::JsCast as _,
+    },
 };
 
+#[cfg(target_arch = "wasm32")]
 const URL_TAG_DEBUG: &str = "debug";
+#[cfg(target_arch = "wasm32")]
 const URL_TAG_ANIMATION: &str = "animation";
+#[cfg(target_arch = "wasm32")]
 const DEBUG_UI_URL_TAGS: &[&str] = &[URL_TAG_DEBUG, URL_TAG_ANIMATION];
 
+#[cfg(target_arch = "wasm32")]
 struct RecorderState {
     recorder: MediaRecorder,
     _data_listener: EventListener,
@@ -51,6 +66,7 @@ impl DebugColor {
     }
 }
 
+#[cfg(target_arch = "wasm32")]
 #[macro_export]
 macro_rules! log {
     ( $( $t:tt )* ) => {
@@ -58,6 +74,15 @@ macro_rules! log {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+#[macro_export]
+macro_rules! log {
+    ( $( $t:tt )* ) => {
+        eprintln!( $( $t )* )
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
 #[macro_export]
 macro_rules! warn {
     ( $( $t:tt )* ) => {
@@ -65,12 +90,21 @@ macro_rules! warn {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+#[macro_export]
+macro_rules! warn {
+    ( $( $t:tt )* ) => {
+        eprintln!( $( $t )* )
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum RestartMode {
     Reload,
     Full,
 }
 
+#[cfg(target_arch = "wasm32")]
 pub enum DebugUIState {
     Enabled {
         root: Element,
@@ -84,6 +118,7 @@ pub enum DebugUIState {
     },
 }
 
+#[cfg(target_arch = "wasm32")]
 impl DebugUIState {
     fn set_restart_mode(&mut self, mode: RestartMode) {
         match self {
@@ -100,15 +135,27 @@ impl DebugUIState {
     }
 }
 
+/// Re-reads a param from the url and updates its widget and value
+#[cfg(target_arch = "wasm32")]
+type UrlUpdater = Box<dyn Fn()>;
+
+#[cfg(target_arch = "wasm32")]
 pub struct DebugUI {
     state: Rc<RefCell<DebugUIState>>,
     _shortcut_listener: EventListener,
+    _popstate_listener: Option<EventListener>,
+    url_updaters: Rc<RefCell<Vec<UrlUpdater>>>,
     _recorder: Rc<RefCell<Option<RecorderState>>>,
     _stopping_recorder: Rc<RefCell<Option<RecorderState>>>,
     document: Document,
     needs_clear_shared: Rc<RefCell<bool>>,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+pub struct DebugUI {
+    needs_clear_shared: Rc<RefCell<bool>>,
+}
+
 pub struct Param<T> {
     inner: Arc<RwLock<T>>,
 }
@@ -130,6 +177,80 @@ pub struct ParamParam<T, S> {
     pub needs_restart: bool,
 }
 
+/// Recoverable failures of the debug UI.
+///
+/// These are logged and skipped instead of crashing the wasm module. Genuine
+/// programmer errors (missing DOM nodes, broken invariants) still panic.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DebugUiError {
+    /// A widget produced a number that doesn't fit in the parameter's type (NaN, out of range..)
+    ValueCast { param: String, value: f64 },
+    /// A value found in the url couldn't be parsed for the parameter
+    InvalidUrlValue { param: String, value: String },
+}
+
+impl fmt::Display for DebugUiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DebugUiError::ValueCast { param, value } => {
+                write!(f, "failed to cast value {value} for parameter {param}")
+            }
+            DebugUiError::InvalidUrlValue { param, value } => {
+                write!(f, "invalid url value {value:?} for parameter {param}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DebugUiError {}
+
+#[cfg(any(target_arch = "wasm32", test))]
+fn cast_value<T: FromPrimitive>(param: &str, value: f64) -> Result<T, DebugUiError> {
+    T::from_f64(value).ok_or_else(|| DebugUiError::ValueCast {
+        param: param.to_owned(),
+        value,
+    })
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+fn parse_url_value<T: FromStr>(param: &str, raw: &str) -> Result<T, DebugUiError> {
+    raw.parse().map_err(|_| DebugUiError::InvalidUrlValue {
+        param: param.to_owned(),
+        value: raw.to_owned(),
+    })
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+fn parse_url_color(param: &str, raw: &str) -> Result<DebugColor, DebugUiError> {
+    DebugColor::from_hex(raw).ok_or_else(|| DebugUiError::InvalidUrlValue {
+        param: param.to_owned(),
+        value: raw.to_owned(),
+    })
+}
+
+/// Value of `key` in the url, falling back to `default` when it is absent or invalid
+#[cfg(target_arch = "wasm32")]
+fn url_value_or<T>(
+    key: &str,
+    default: T,
+    parse: impl FnOnce(&str, &str) -> Result<T, DebugUiError>,
+) -> T {
+    let Some(raw) = get_url_param(key) else {
+        forget_url_param(key);
+        return default;
+    };
+    match parse(key, &raw) {
+        Ok(value) => {
+            remember_url_param(key, raw);
+            value
+        }
+        Err(err) => {
+            warn!("[DebugUI] {err}, using default value");
+            default
+        }
+    }
+}
+
 #[derive(Clone, Copy, Default, Debug)]
 pub enum Scale {
     #[default]
@@ -155,6 +276,7 @@ impl<T: Num> Default for ParamParam<T, &str> {
 }
 
 impl<T: Copy> Param<T> {
+    #[cfg(target_arch = "wasm32")]
     fn new(value: T) -> (Arc<RwLock<T>>, Self) {
         let inner = Arc::new(RwLock::new(value));
         (Arc::clone(&inner), Self { inner })
@@ -211,13 +333,58 @@ impl StepCounter {
     }
 }
 
+/// Runtime switch for writing param values in the url, initially on when the
+/// `save-params-in-url` feature is enabled
+static SAVE_PARAMS_IN_URL: AtomicBool = AtomicBool::new(cfg!(feature = "save-params-in-url"));
+
 thread_local! {
+    #[cfg(target_arch = "wasm32")]
     static HISTORY_PUSHED: RefCell<bool> = const { RefCell::new(false) };
+    /// Set while params are being updated from the url after a history navigation,
+    /// so that the updates don't write the url back
+    #[cfg(any(target_arch = "wasm32", test))]
+    static APPLYING_URL: RefCell<bool> = const { RefCell::new(false) };
+    /// Last url value of every param, kept even while url saving is off so that
+    /// turning it back on restores them
+    #[cfg(target_arch = "wasm32")]
+    static PARAM_URL_VALUES: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+}
+
+/// Writes the tracked param `values` to the url `params`, or removes them from it.
+/// Other keys of the url (e.g. `debug` or `worker`) are left alone.
+#[cfg(any(target_arch = "wasm32", test))]
+fn save_params_in_url(
+    params: &mut HashMap<String, String>,
+    values: &HashMap<String, String>,
+    enabled: bool,
+) {
+    if enabled {
+        params.extend(values.iter().map(|(k, v)| (k.clone(), v.clone())));
+    } else {
+        params.retain(|k, _| !values.contains_key(k));
+    }
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+fn applying_url() -> bool {
+    APPLYING_URL.with(|applying| *applying.borrow())
 }
 
+/// Run `f` with url writes disabled
+#[cfg(any(target_arch = "wasm32", test))]
+fn while_applying_url(f: impl FnOnce()) {
+    APPLYING_URL.with(|applying| *applying.borrow_mut() = true);
+    f();
+    APPLYING_URL.with(|applying| *applying.borrow_mut() = false);
+}
+
+#[cfg(target_arch = "wasm32")]
 fn push_or_replace_url(new_url: &str) {
     use common::window;
     use web_sys::wasm_bindgen::JsValue;
+    if applying_url() {
+        return;
+    }
     let history = window().history().unwrap();
     HISTORY_PUSHED.with(|pushed| {
         if *pushed.borrow() {
@@ -233,6 +400,7 @@ fn push_or_replace_url(new_url: &str) {
     });
 }
 
+#[cfg(target_arch = "wasm32")]
 fn modify_url_params(f: impl FnOnce(&mut HashMap<String, String>)) {
     let mut new_url = common::url();
     let mut params: HashMap<String, String> = new_url
@@ -247,15 +415,35 @@ fn modify_url_params(f: impl FnOnce(&mut HashMap<String, String>)) {
     push_or_replace_url(new_url.as_str());
 }
 
+#[cfg(target_arch = "wasm32")]
+fn remember_url_param(key: &str, value: String) {
+    PARAM_URL_VALUES.with(|values| values.borrow_mut().insert(key.to_owned(), value));
+}
+
+#[cfg(target_arch = "wasm32")]
+fn forget_url_param(key: &str) {
+    PARAM_URL_VALUES.with(|values| values.borrow_mut().remove(key));
+}
+
+#[cfg(target_arch = "wasm32")]
+fn set_url_param(key: &str, value: String) {
+    remember_url_param(key, value.clone());
+    if !SAVE_PARAMS_IN_URL.load(Ordering::Relaxed) {
+        return;
+    }
+    modify_url_params(|params| {
+        params.insert(key.to_owned(), value);
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
 fn add_url_param<T: Copy + ToString + FromStr + ToPrimitive + FromPrimitive + 'static>(
     key: &str,
     value: T,
 ) {
-    modify_url_params(|params| {
-        params.retain(|k, _| k != key);
-        params.insert(key.into(), value.to_string());
-    });
+    set_url_param(key, value.to_string());
 }
+#[cfg(target_arch = "wasm32")]
 fn add_url_param_empty(key: &str) {
     modify_url_params(|params| {
         params.retain(|k, _| k != key);
@@ -263,22 +451,26 @@ fn add_url_param_empty(key: &str) {
     });
 }
 
+#[cfg(target_arch = "wasm32")]
 fn add_debug_url_param() {
     add_url_param_empty(URL_TAG_DEBUG);
 }
 
+#[cfg(target_arch = "wasm32")]
 fn remove_url_param(key: &str) {
     modify_url_params(|params| {
         params.retain(|k, _| k != key);
     });
 }
 
+#[cfg(target_arch = "wasm32")]
 fn remove_all_url_params_except(keys: &[&str]) {
     modify_url_params(|params| {
         params.retain(|k, _| keys.contains(&k.as_str()));
     });
 }
 
+#[cfg(target_arch = "wasm32")]
 impl DebugUI {
     fn register_shortcut(
         state: Rc<RefCell<DebugUIState>>,
@@ -409,27 +601,11 @@ impl DebugUI {
                             let options = BlobPropertyBag::new();
                             let blob =
                                 Blob::new_with_blob_sequence_and_options(&array, &options).unwrap();
-                            let url = Url::create_object_url_with_blob(&blob).unwrap();
-
-                            let doc = document();
-                            let a = doc
-                                .create_element("a")
-                                .unwrap()
-                                .dyn_into::<HtmlAnchorElement>()
-                                .unwrap();
-                            a.set_href(&url);
                             let animation_name =
                                 get_url_param("animation").unwrap_or("menu".to_owned());
                             let now = Date::new_0().to_iso_string();
                             let file_name = format!("recording-{animation_name}-{now}.webm");
-                            a.set_download(&file_name);
-                            a.click();
-
-                            let url_to_revoke = url.clone();
-                            gloo::timers::callback::Timeout::new(1000, move || {
-                                let _ = Url::revoke_object_url(&url_to_revoke);
-                            })
-                            .forget();
+                            download_blob(&blob, &file_name);
 
                             // Clear stopping state
                             *stopping_recorder_done.borrow_mut() = None;
@@ -451,6 +627,22 @@ impl DebugUI {
         })
     }
 
+    /// Browser back/forward: update every param from the new url
+    fn register_popstate(updaters: Rc<RefCell<Vec<UrlUpdater>>>) -> EventListener {
+        EventListener::new(&window(), "popstate", move |_event| {
+            // the current entry was not pushed by us, the next change gets its own entry
+            HISTORY_PUSHED.with(|pushed| *pushed.borrow_mut() = false);
+            if !Self::saves_params_in_url() {
+                return;
+            }
+            while_applying_url(|| {
+                for update in updaters.borrow().iter() {
+                    update();
+                }
+            });
+        })
+    }
+
     pub fn new(title: impl AsRef<str>) -> Self {
         {
             let document = document();
@@ -483,9 +675,13 @@ impl DebugUI {
 
             let shortcut_listener =
                 Self::register_shortcut(state.clone(), recorder.clone(), stopping_recorder.clone());
+            let url_updaters = Rc::new(RefCell::new(Vec::new()));
+            let popstate_listener = Self::register_popstate(url_updaters.clone());
             Self {
                 state,
                 _shortcut_listener: shortcut_listener,
+                _popstate_listener: Some(popstate_listener),
+                url_updaters,
                 _recorder: recorder,
                 _stopping_recorder: stopping_recorder,
                 document,
@@ -510,6 +706,8 @@ impl DebugUI {
             Self {
                 state,
                 _shortcut_listener: shortcut_listener,
+                _popstate_listener: None,
+                url_updaters: Rc::new(RefCell::new(Vec::new())),
                 _recorder: recorder,
                 _stopping_recorder: stopping_recorder,
                 document,
@@ -518,6 +716,18 @@ impl DebugUI {
         }
     }
 
+    /// Turn saving the param values in the url on or off. Turning it off removes them
+    /// from the url, turning it back on writes their current values again.
+    pub fn set_save_params_in_url(enabled: bool) {
+        SAVE_PARAMS_IN_URL.store(enabled, Ordering::Relaxed);
+        let values = PARAM_URL_VALUES.with(|values| values.borrow().clone());
+        modify_url_params(|params| save_params_in_url(params, &values, enabled));
+    }
+
+    pub fn saves_params_in_url() -> bool {
+        SAVE_PARAMS_IN_URL.load(Ordering::Relaxed)
+    }
+
     pub fn is_enabled(&self) -> bool {
         matches!(*self.state.borrow(), DebugUIState::Enabled { .. })
     }
@@ -537,7 +747,7 @@ impl DebugUI {
     }
 
     pub fn param<
-        T: Copy + ToString + FromStr + ToPrimitive + FromPrimitive + 'static,
+        T: Copy + PartialEq + ToString + FromStr + ToPrimitive + FromPrimitive + 'static,
         S: AsRef<str> + Clone,
     >(
         &mut self,
@@ -545,14 +755,7 @@ impl DebugUI {
     ) -> Param<T> {
         {
             let key = p.name.as_ref().replace(" ", "_");
-            let default_value = common::url()
-                .query_pairs()
-                .find(|(k, _)| k.as_ref() == key)
-                .map(|(_, v)| v.parse())
-                .into_iter()
-                .flatten()
-                .next()
-                .unwrap_or(p.default_value);
+            let default_value = url_value_or(&key, p.default_value, parse_url_value);
 
             let (writer, param_value) = Param::new(default_value);
             let doc = self.document.clone();
@@ -643,9 +846,13 @@ impl DebugUI {
                                 .unwrap()
                                 .dyn_into::<HtmlInputElement>()
                                 .unwrap();
-                            let value = T::from_f64(scaled).unwrap_or_else(|| {
-                                panic!("Failed to cast slider value for parameter {name}")
-                            });
+                            let value = match cast_value::<T>(&name, scaled) {
+                                Ok(value) => value,
+                                Err(err) => {
+                                    warn!("[DebugUI] {err}");
+                                    return;
+                                }
+                            };
 
                             value_input.set_value_as_number(value.to_f64().unwrap());
 
@@ -684,9 +891,13 @@ impl DebugUI {
                             // TODO: add range check here?
                             slider_input.set_value_as_number(unscaled);
 
-                            let value = T::from_f64(value).unwrap_or_else(|| {
-                                panic!("Failed to cast slider value for parameter {name}")
-                            });
+                            let value = match cast_value::<T>(&name, value) {
+                                Ok(value) => value,
+                                Err(err) => {
+                                    warn!("[DebugUI] {err}");
+                                    return;
+                                }
+                            };
 
                             add_url_param(&key, value);
 
@@ -697,6 +908,22 @@ impl DebugUI {
                         })
                         .forget();
                     }
+                    {
+                        let writer = Arc::clone(&writer);
+                        let state = state.clone();
+                        self.url_updaters.borrow_mut().push(Box::new(move || {
+                            let value = url_value_or(&key, p.default_value, parse_url_value);
+                            if *writer.read().unwrap() == value {
+                                return;
+                            }
+                            slider.set_value_as_number(p.scale.unscale(value, &p.range));
+                            value_input.set_value_as_number(value.to_f64().unwrap());
+                            *writer.write().unwrap() = value;
+                            if p.needs_restart {
+                                Self::set_restart_mode(&state, RestartMode::Reload);
+                            }
+                        }));
+                    }
                 }
             }
             param_value
@@ -706,11 +933,7 @@ impl DebugUI {
     pub fn color_param(&mut self, name: &str, default: DebugColor) -> Param<DebugColor> {
         {
             let key = name.replace(" ", "_");
-            let default_value = common::url()
-                .query_pairs()
-                .find(|(k, _)| k.as_ref() == key)
-                .and_then(|(_, v)| DebugColor::from_hex(v.as_ref()))
-                .unwrap_or(default);
+            let default_value = url_value_or(&key, default, parse_url_color);
 
             let (writer, param_value) = Param::new(default_value);
             let doc = self.document.clone();
@@ -775,15 +998,91 @@ impl DebugUI {
                                     )
                                     .unwrap();
                                 *writer.write().unwrap() = color;
-                                let key = key.clone();
-                                modify_url_params(|params| {
-                                    params.retain(|k, _| k != &key);
-                                    params.insert(key.clone(), color.to_hex());
-                                });
+                                set_url_param(&key, color.to_hex());
                             }
                         })
                         .forget();
                     }
+                    {
+                        let writer = Arc::clone(&writer);
+                        self.url_updaters.borrow_mut().push(Box::new(move || {
+                            let color = url_value_or(&key, default, parse_url_color);
+                            if *writer.read().unwrap() == color {
+                                return;
+                            }
+                            preview
+                                .set_attribute(
+                                    "style",
+                                    &format!("background-color: {}", color.to_hex()),
+                                )
+                                .unwrap();
+                            color_input.set_value(&color.to_hex());
+                            *writer.write().unwrap() = color;
+                        }));
+                    }
+                }
+            }
+            param_value
+        }
+    }
+
+    pub fn param_bool(&mut self, name: &str, default: bool) -> Param<bool> {
+        {
+            let key = name.replace(" ", "_");
+            let default_value = url_value_or(&key, default, parse_url_value);
+
+            let (writer, param_value) = Param::new(default_value);
+            let doc = self.document.clone();
+            let state = self.state.clone();
+            let mut state_match = state.borrow_mut();
+            match &mut *state_match {
+                DebugUIState::Enabled { root, next_uid, .. }
+                | DebugUIState::Disabled { root, next_uid, .. } => {
+                    let container = doc.create_element("div").unwrap();
+                    let label = doc.create_element("label").unwrap();
+                    let checkbox = doc
+                        .create_element("input")
+                        .unwrap()
+                        .dyn_into::<HtmlInputElement>()
+                        .unwrap();
+
+                    let uid = *next_uid;
+                    *next_uid += 1;
+                    let checkbox_id = format!("debugui-checkbox-{uid}");
+
+                    checkbox.set_id(&checkbox_id);
+                    checkbox.set_attribute("type", "checkbox").unwrap();
+                    checkbox.set_checked(default_value);
+                    label.set_text_content(Some(name));
+                    label.set_attribute("for", &checkbox_id).unwrap();
+
+                    container.set_class_name("DebugUI-param-container");
+                    label.set_class_name("DebugUI-param-label");
+                    checkbox.set_class_name("DebugUI-param-checkbox");
+
+                    container.append_child(&label).unwrap();
+                    container.append_child(&checkbox).unwrap();
+                    root.append_child(&container).unwrap();
+
+                    {
+                        let writer = Arc::clone(&writer);
+                        let checkbox_clone = checkbox.clone();
+                        let key = key.clone();
+                        EventListener::new(&checkbox, "change", move |_event| {
+                            let value = checkbox_clone.checked();
+                            *writer.write().unwrap() = value;
+                            set_url_param(&key, value.to_string());
+                        })
+                        .forget();
+                    }
+                    {
+                        let writer = Arc::clone(&writer);
+                        self.url_updaters.borrow_mut().push(Box::new(move || {
+                            let value = url_value_or(&key, default, parse_url_value);
+                            checkbox.set_checked(value);
+                            *writer.write().unwrap() = value;
+                        }));
+                    }
                 }
             }
             param_value
@@ -858,6 +1157,23 @@ impl DebugUI {
         }
     }
 
+    /// Button whose clicks set the returned flag, for the animation to pick up and reset
+    pub fn button(&mut self, text: &str) -> Rc<RefCell<bool>> {
+        let clicked = Rc::new(RefCell::new(false));
+        let btn = self.document.create_element("button").unwrap();
+        btn.set_text_content(Some(text));
+        btn.set_class_name("DebugUI-btn");
+        self.root().append_child(&btn).unwrap();
+        {
+            let clicked = clicked.clone();
+            EventListener::new(&btn, "click", move |_event| {
+                *clicked.borrow_mut() = true;
+            })
+            .forget();
+        }
+        clicked
+    }
+
     pub fn take_restart_mode(&mut self) -> Option<RestartMode> {
         self.state.borrow_mut().take_restart_mode()
     }
@@ -903,6 +1219,13 @@ impl DebugUI {
         let close_btn = document.create_element("button").unwrap();
         let reset_btn = document.create_element("button").unwrap();
         let clear_btn = document.create_element("button").unwrap();
+        let url_toggle = document.create_element("div").unwrap();
+        let url_toggle_label = document.create_element("label").unwrap();
+        let url_toggle_checkbox = document
+            .create_element("input")
+            .unwrap()
+            .dyn_into::<HtmlInputElement>()
+            .unwrap();
 
         title_elt.set_text_content(Some(title.as_ref()));
         fullscreen_btn.set_text_content(Some("🎦"));
@@ -910,6 +1233,15 @@ impl DebugUI {
         close_btn.set_text_content(Some("❌"));
         reset_btn.set_text_content(Some("Reset params"));
         clear_btn.set_text_content(Some("Clear canvas"));
+        url_toggle_label.set_text_content(Some("save params in url"));
+        url_toggle_label
+            .set_attribute("for", "debugui-save-params-in-url")
+            .unwrap();
+        url_toggle_checkbox.set_id("debugui-save-params-in-url");
+        url_toggle_checkbox
+            .set_attribute("type", "checkbox")
+            .unwrap();
+        url_toggle_checkbox.set_checked(Self::saves_params_in_url());
 
         root.set_class_name("DebugUI-root-box");
         title_elt.set_class_name("DebugUI-title");
@@ -919,6 +1251,9 @@ impl DebugUI {
         close_btn.set_class_name("DebugUI-close-btn");
         reset_btn.set_class_name("DebugUI-reset-btn");
         clear_btn.set_class_name("DebugUI-clear-btn");
+        url_toggle.set_class_name("DebugUI-param-container");
+        url_toggle_label.set_class_name("DebugUI-param-label");
+        url_toggle_checkbox.set_class_name("DebugUI-param-checkbox");
 
         title_line.append_child(&title_elt).unwrap();
         title_line.append_child(&fullscreen_btn).unwrap();
@@ -927,6 +1262,9 @@ impl DebugUI {
         root.append_child(&title_line).unwrap();
         root.append_child(&reset_btn).unwrap();
         root.append_child(&clear_btn).unwrap();
+        url_toggle.append_child(&url_toggle_label).unwrap();
+        url_toggle.append_child(&url_toggle_checkbox).unwrap();
+        root.append_child(&url_toggle).unwrap();
 
         let container = get_canvas_parent().unwrap();
         container.append_child(&root).unwrap();
@@ -1006,6 +1344,13 @@ impl DebugUI {
             })
             .forget();
         }
+        {
+            let checkbox = url_toggle_checkbox.clone();
+            EventListener::new(&url_toggle_checkbox, "change", move |_event| {
+                Self::set_save_params_in_url(checkbox.checked());
+            })
+            .forget();
+        }
         DebugUIState::Enabled {
             root,
             next_uid: 0,
@@ -1147,14 +1492,86 @@ impl DebugUI {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+impl DebugUI {
+    pub fn new(_title: impl AsRef<str>) -> Self {
+        Self::headless()
+    }
+
+    pub fn headless() -> Self {
+        Self {
+            needs_clear_shared: Rc::new(RefCell::new(false)),
+        }
+    }
+
+    pub fn set_save_params_in_url(enabled: bool) {
+        SAVE_PARAMS_IN_URL.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn saves_params_in_url() -> bool {
+        SAVE_PARAMS_IN_URL.load(Ordering::Relaxed)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        false
+    }
+
+    pub fn start_section<S: AsRef<str>>(&mut self, _title: S) {}
+
+    pub fn param<
+        T: Copy + PartialEq + ToString + FromStr + ToPrimitive + FromPrimitive + 'static,
+        S: AsRef<str> + Clone,
+    >(
+        &mut self,
+        p: ParamParam<T, S>,
+    ) -> Param<T> {
+        Param::fixed(p.default_value)
+    }
+
+    pub fn color_param(&mut self, _name: &str, default: DebugColor) -> Param<DebugColor> {
+        Param::fixed(default)
+    }
+
+    pub fn param_bool(&mut self, _name: &str, default: bool) -> Param<bool> {
+        Param::fixed(default)
+    }
+
+    pub fn presets(&mut self, _presets: &[(&'static str, &'static str)]) {}
+
+    pub fn link(&mut self, _text: &str, _href: &str) {}
+
+    pub fn button(&mut self, _text: &str) -> Rc<RefCell<bool>> {
+        Rc::new(RefCell::new(false))
+    }
+
+    pub fn take_restart_mode(&mut self) -> Option<RestartMode> {
+        None
+    }
+
+    pub fn needs_clear(&self) -> Rc<RefCell<bool>> {
+        self.needs_clear_shared.clone()
+    }
+
+    pub fn step_counter(&mut self) -> StepCounter {
+        StepCounter::disabled()
+    }
+
+    pub fn add_footer(&mut self) {}
+
+    pub fn ai_impl_dropdown(&mut self) {}
+}
+
+#[cfg(target_arch = "wasm32")]
 fn reload() {
     window().location().reload().unwrap();
 }
 
+#[cfg(target_arch = "wasm32")]
 fn has_url_tag(tag: &str) -> bool {
     common::url().query_pairs().any(|param| param.0 == tag)
 }
 
+#[cfg(target_arch = "wasm32")]
 fn get_url_param(tag: &str) -> Option<String> {
     common::url()
         .query_pairs()
@@ -1162,6 +1579,7 @@ fn get_url_param(tag: &str) -> Option<String> {
         .map(|(_, value)| value.into_owned())
 }
 
+#[cfg(target_arch = "wasm32")]
 fn close_debug_ui(root: &Element, state: &Option<Rc<RefCell<DebugUIState>>>) {
     remove_url_param(URL_TAG_DEBUG);
     root.set_attribute("style", "display: none").unwrap();
@@ -1180,6 +1598,7 @@ fn close_debug_ui(root: &Element, state: &Option<Rc<RefCell<DebugUIState>>>) {
     }
 }
 
+#[cfg(target_arch = "wasm32")]
 impl Drop for DebugUI {
     fn drop(&mut self) {
         {
@@ -1193,6 +1612,7 @@ impl Drop for DebugUI {
     }
 }
 
+#[cfg(any(target_arch = "wasm32", test))]
 impl Scale {
     // these doc strings are only true for Logarithmic scale smh..
 
@@ -1235,8 +1655,12 @@ impl Scale {
 
 #[cfg(test)]
 mod tests {
-    use super::{DebugColor, Scale, StepCounter};
+    use super::{
+        DebugColor, DebugUI, DebugUiError, Scale, StepCounter, applying_url, cast_value,
+        parse_url_color, parse_url_value, save_params_in_url, while_applying_url,
+    };
     use rstest::rstest;
+    use std::collections::HashMap;
 
     #[test]
     fn step_counter_add_steps() {
@@ -1318,6 +1742,101 @@ mod tests {
         assert_eq!(original, recovered);
     }
 
+    #[rstest]
+    #[case(42.0, Ok(42))]
+    #[case(0.0, Ok(0))]
+    #[case(255.0, Ok(255))]
+    #[case(256.0, Err(DebugUiError::ValueCast { param: "p".into(), value: 256.0 }))]
+    #[case(-1.0, Err(DebugUiError::ValueCast { param: "p".into(), value: -1.0 }))]
+    fn cast_value_u8(#[case] value: f64, #[case] expected: Result<u8, DebugUiError>) {
+        assert_eq!(cast_value::<u8>("p", value), expected);
+    }
+
+    #[test]
+    fn cast_value_nan_is_recoverable() {
+        // an emptied number input yields NaN, this must not crash the module
+        let err = cast_value::<usize>("cell size", f64::NAN).unwrap_err();
+        assert!(matches!(err, DebugUiError::ValueCast { ref param, .. } if param == "cell size"));
+        assert_eq!(
+            err.to_string(),
+            "failed to cast value NaN for parameter cell size"
+        );
+    }
+
+    #[test]
+    fn save_params_in_url_defaults_to_feature() {
+        assert_eq!(
+            DebugUI::saves_params_in_url(),
+            cfg!(feature = "save-params-in-url")
+        );
+    }
+
+    #[rstest]
+    #[case(true, &[("debug", ""), ("worker", ""), ("seed", "3"), ("cell_size", "7")])]
+    #[case(false, &[("debug", ""), ("worker", "")])]
+    fn save_params_in_url_only_touches_tracked_params(
+        #[case] enabled: bool,
+        #[case] expected: &[(&str, &str)],
+    ) {
+        let to_map = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let mut params = to_map(&[("debug", ""), ("worker", ""), ("seed", "1")]);
+        let values = to_map(&[("seed", "3"), ("cell_size", "7")]);
+        save_params_in_url(&mut params, &values, enabled);
+        assert_eq!(params, to_map(expected));
+    }
+
+    #[test]
+    fn applying_url_guard_is_scoped() {
+        assert!(!applying_url());
+        let mut inside = false;
+        while_applying_url(|| inside = applying_url());
+        assert!(inside);
+        assert!(!applying_url());
+    }
+
+    #[test]
+    fn parse_url_value_valid() {
+        assert_eq!(parse_url_value::<f32>("start_x", "0.5"), Ok(0.5));
+        assert_eq!(parse_url_value::<usize>("number_of_ants", "400"), Ok(400));
+    }
+
+    #[rstest]
+    #[case("abc")]
+    #[case("")]
+    #[case("-3")]
+    #[case("1.5")]
+    fn parse_url_value_invalid(#[case] raw: &str) {
+        assert_eq!(
+            parse_url_value::<usize>("cell_size", raw),
+            Err(DebugUiError::InvalidUrlValue {
+                param: "cell_size".into(),
+                value: raw.into()
+            })
+        );
+    }
+
+    #[test]
+    fn parse_url_color_valid_and_invalid() {
+        assert_eq!(
+            parse_url_color("bg", "#0D1117"),
+            Ok(DebugColor {
+                r: 13,
+                g: 17,
+                b: 23
+            })
+        );
+        let err = parse_url_color("bg", "#nope").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r##"invalid url value "#nope" for parameter bg"##
+        );
+    }
+
     #[rstest]
     #[case(Scale::Linear, 0.1, 0., 1000., 0.1)]
     #[case(Scale::Linear, 1000., 0., 0., 1000.)] // validation is not this function's job
@@ -8,6 +8,9 @@ pub trait Simulation {
     /// Execute one simulation step. Called N times per frame based on speed config.
     fn step(&mut self, canvas: &mut Canvas);
 
+    /// Called once per frame after all of the frame's steps.
+    fn end_frame(&mut self, _canvas: &mut Canvas) {}
+
     /// Called when canvas dimensions change. Sim should adapt its state.
     fn on_canvas_resize(&mut self, new_width: usize, new_height: usize);
 
@@ -96,6 +99,7 @@ impl<S: Simulation> SimulationRunner<S> {
                 steps_this_frame += 1;
                 self.sim.step(canvas);
             }
+            self.sim.end_frame(canvas);
 
             self.step_counter.borrow_mut().add_steps(steps_this_frame);
             canvas.fill_canvas(
@@ -1,4 +1,4 @@
-use darling::{FromDeriveInput, FromField};
+use darling::{FromDeriveInput, FromField, FromMeta};
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::{DeriveInput, parse_macro_input};
@@ -14,8 +14,9 @@ struct ConfigOpts {
 #[darling(attributes(param))]
 struct ParamFieldOpts {
     ident: Option<syn::Ident>,
+    ty: syn::Type,
     name: String,
-    default: String,
+    default: DefaultValue,
     #[darling(default)]
     range: Option<String>,
     #[darling(default)]
@@ -30,6 +31,87 @@ struct ParamFieldOpts {
     color: bool,
 }
 
+/// `default = "<expr>"` for any field, or a bare `default = true` / `default = false` for bool fields
+enum DefaultValue {
+    Expr(String),
+    Bool(bool),
+}
+
+impl FromMeta for DefaultValue {
+    fn from_string(value: &str) -> darling::Result<Self> {
+        Ok(Self::Expr(value.to_owned()))
+    }
+
+    fn from_bool(value: bool) -> darling::Result<Self> {
+        Ok(Self::Bool(value))
+    }
+}
+
+/// Whether `ty` is a path ending in `ident` without generic arguments, e.g. `bool`
+fn is_type(ty: &syn::Type, ident: &str) -> bool {
+    let syn::Type::Path(path) = ty else {
+        return false;
+    };
+    path.path
+        .segments
+        .last()
+        .is_some_and(|last| last.ident == ident && last.arguments.is_none())
+}
+
+/// `Param<bool>`
+fn is_bool_type(ty: &syn::Type) -> bool {
+    let syn::Type::Path(path) = ty else {
+        return false;
+    };
+    let Some(last) = path.path.segments.last() else {
+        return false;
+    };
+    match &last.arguments {
+        syn::PathArguments::AngleBracketed(args) if last.ident == "Param" => {
+            matches!(args.args.first(), Some(syn::GenericArgument::Type(inner)) if is_type(inner, "bool"))
+        }
+        _ => false,
+    }
+}
+
+/// Checks the attributes of a bool field and returns its default value
+fn bool_field_default(field: &ParamFieldOpts, field_name: &syn::Ident) -> darling::Result<bool> {
+    let mut errors = darling::Error::accumulator();
+    for (attr, present) in [
+        ("range", field.range.is_some()),
+        ("step", field.step.is_some()),
+        ("scale", field.scale.is_some()),
+        ("color", field.color),
+    ] {
+        if present {
+            errors.push(
+                darling::Error::custom(format!(
+                    "`{attr}` can't be used on bool field `{field_name}`, it is displayed as a checkbox"
+                ))
+                .with_span(field_name),
+            );
+        }
+    }
+    let default = match &field.default {
+        DefaultValue::Bool(value) => Some(*value),
+        DefaultValue::Expr(expr) => match expr.trim() {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => {
+                errors.push(
+                    darling::Error::custom(format!(
+                        "bool field `{field_name}` needs `default = true` or `default = false`, got {expr:?}"
+                    ))
+                    .with_span(field_name),
+                );
+                None
+            }
+        },
+    };
+    errors.finish()?;
+    Ok(default.unwrap())
+}
+
 fn parse_range_tokens(range_str: &str) -> proc_macro2::TokenStream {
     range_str
         .parse()
@@ -58,11 +140,11 @@ pub fn derive_simulation_config(input: TokenStream) -> TokenStream {
 
     let mut section_stmts: Vec<proc_macro2::TokenStream> = vec![];
     let mut field_inits: Vec<proc_macro2::TokenStream> = vec![];
+    let mut errors = darling::Error::accumulator();
 
     for field in fields.iter() {
         let field_name = field.ident.as_ref().unwrap();
         let name = &field.name;
-        let default_val = parse_default_tokens(&field.default);
 
         if let Some(section) = &field.section {
             section_stmts.push(quote! {
@@ -70,6 +152,39 @@ pub fn derive_simulation_config(input: TokenStream) -> TokenStream {
             });
         }
 
+        if is_type(&field.ty, "bool") {
+            errors.push(
+                darling::Error::custom(format!(
+                    "bool field `{field_name}` must be a `Param<bool>`, it is displayed as a checkbox"
+                ))
+                .with_span(field_name),
+            );
+            continue;
+        }
+
+        if is_bool_type(&field.ty) {
+            if let Some(default_val) = errors.handle(bool_field_default(field, field_name)) {
+                section_stmts.push(quote! {
+                    let #field_name = debug_ui.param_bool(#name, #default_val);
+                });
+            }
+            field_inits.push(quote! { #field_name });
+            continue;
+        }
+
+        let default_val = match &field.default {
+            DefaultValue::Expr(expr) => parse_default_tokens(expr),
+            DefaultValue::Bool(_) => {
+                errors.push(
+                    darling::Error::custom(format!(
+                        "`default = true/false` is only allowed on bool fields, `{field_name}` isn't one"
+                    ))
+                    .with_span(field_name),
+                );
+                continue;
+            }
+        };
+
         let range_expr = field.range.as_ref().map(|r| {
             let tokens = parse_range_tokens(r);
             quote! { range: #tokens, }
@@ -111,6 +226,10 @@ pub fn derive_simulation_config(input: TokenStream) -> TokenStream {
         field_inits.push(quote! { #field_name });
     }
 
+    if let Err(e) = errors.finish() {
+        return e.write_errors().into();
+    }
+
     let expanded = quote! {
         impl #struct_name {
             pub fn new(debug_ui: &mut debug_ui::DebugUI) -> Self {
@@ -0,0 +1,9 @@
+use engine_macros::SimulationConfig;
+
+#[derive(SimulationConfig)]
+struct Config {
+    #[param(name = "show grid", default = true)]
+    show_grid: bool,
+}
+
+fn main() {}
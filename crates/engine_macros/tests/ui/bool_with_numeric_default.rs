@@ -0,0 +1,13 @@
+use engine_macros::SimulationConfig;
+
+struct Param<T>(T);
+
+#[derive(SimulationConfig)]
+struct Config {
+    #[param(name = "show grid", default = "1")]
+    show_grid: Param<bool>,
+    #[param(name = "cell size", default = true)]
+    cell_size: Param<usize>,
+}
+
+fn main() {}
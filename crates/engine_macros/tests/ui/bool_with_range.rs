@@ -0,0 +1,11 @@
+use engine_macros::SimulationConfig;
+
+struct Param<T>(T);
+
+#[derive(SimulationConfig)]
+struct Config {
+    #[param(name = "show grid", default = true, range = "0..=1", step = 1.0)]
+    show_grid: Param<bool>,
+}
+
+fn main() {}
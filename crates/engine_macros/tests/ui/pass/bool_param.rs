@@ -0,0 +1,13 @@
+use debug_ui::Param;
+use engine_macros::SimulationConfig;
+
+#[derive(SimulationConfig)]
+struct Config {
+    #[param(name = "show grid", default = true)]
+    show_grid: Param<bool>,
+}
+
+fn main() {
+    let config = Config::new(&mut debug_ui::DebugUI::headless());
+    assert!(config.show_grid.get());
+}
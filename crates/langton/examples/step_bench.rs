@@ -0,0 +1,58 @@
+//! Times the step loop of [`LangtonCore`] with 1000 ants, run natively with
+//! `cargo run --release -p langton --example step_bench --target x86_64-unknown-linux-gnu`
+//!
+//! The checksum of the changes must not move when only the speed of the loop changes.
+
+use std::time::{Duration, Instant};
+
+use canvas::Color;
+use langton::{CoreParams, LangtonCore, Vision, encode_changes};
+
+const WIDTH: usize = 480;
+const HEIGHT: usize = 270;
+const STEPS: usize = 20_000;
+const STEPS_PER_FRAME: usize = 100;
+const RUNS: usize = 5;
+
+fn run(params: &CoreParams) -> (Duration, u64) {
+    let mut core = LangtonCore::new(WIDTH, HEIGHT);
+    let mut elapsed = Duration::ZERO;
+    let mut checksum = 0u64;
+    for _ in 0..STEPS / STEPS_PER_FRAME {
+        let start = Instant::now();
+        for _ in 0..STEPS_PER_FRAME {
+            core.step(params);
+        }
+        elapsed += start.elapsed();
+        for value in encode_changes(core.last_changes()) {
+            checksum = checksum.wrapping_mul(31).wrapping_add(value as u64);
+        }
+        core.drain_changes();
+    }
+    (elapsed, checksum)
+}
+
+fn main() {
+    let params = CoreParams {
+        num_ants: 1000,
+        start_x_rel: 0.5,
+        start_y_rel: 0.5,
+        ant_color_saturation: 0.3,
+        ant_color_brightness: 0.7,
+        seed: 0,
+        vision: Vision::SelfOnly,
+        bg_color: Color::Rgb {
+            r: 30,
+            g: 30,
+            b: 30,
+        },
+        hue_cycle_speed: 0.0,
+        hue_cycle_trails: false,
+    };
+    let runs: Vec<_> = (0..RUNS).map(|_| run(&params)).collect();
+    let best = runs.iter().map(|(elapsed, _)| *elapsed).min().unwrap();
+    println!(
+        "{STEPS} steps of {} ants: best of {RUNS} runs {best:?}, checksum {}",
+        params.num_ants, runs[0].1
+    );
+}
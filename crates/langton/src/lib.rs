@@ -1,9 +1,12 @@
 use std::{cell::RefCell, rc::Rc};
 
-use canvas::{Canvas, Color};
+use canvas::{Canvas, Color, NamedColor};
 use debug_ui::{DebugColor, DebugUI, Param};
 use engine::Simulation;
 use engine_macros::SimulationConfig;
+#[cfg(target_arch = "wasm32")]
+use web_sys::{Blob, BlobPropertyBag, js_sys};
+
 #[derive(SimulationConfig)]
 pub struct GameConfig {
     #[param(
@@ -45,6 +48,9 @@ pub struct GameConfig {
         step = 0.01
     )]
     pub ant_color_brightness: Param<f32>,
+    /// see [`Vision::Ahead`]
+    #[param(name = "ant looks ahead", default = false)]
+    pub looks_ahead: Param<bool>,
     #[param(
         section = "Visual",
         name = "cell size",
@@ -60,6 +66,16 @@ pub struct GameConfig {
         color
     )]
     pub common_cell_color: Param<DebugColor>,
+    /// in degrees per frame, see [`LangtonCore::advance_hue`]
+    #[param(
+        name = "hue cycle speed",
+        default = "0.0",
+        range = "0.0..=10.0",
+        step = 0.1
+    )]
+    pub hue_cycle_speed: Param<f32>,
+    #[param(name = "hue cycle trails", default = false)]
+    pub hue_cycle_trails: Param<bool>,
     #[param(
         section = "Advanced",
         name = "seed",
@@ -105,7 +121,7 @@ pub const LANGTON_PRESETS: &[(&str, &str)] = &[
     ),
 ];
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 enum Direction {
     #[default]
     North,
@@ -134,131 +150,430 @@ impl Direction {
     }
 }
 
-pub struct Game {
-    ants: Vec<Ant>,
-    board: Vec<Option<usize>>,
-    config: Rc<RefCell<GameConfig>>,
+/// Turn rule of the simulation, one letter per cell state
+const RULE: &str = "RL";
+
+/// Neighborhood an ant consults to decide which way to turn.
+/// Whatever it sees, the ant only ever flips the cell it stands on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Vision {
+    /// Classic Langton rule: turn right on an empty cell, left on a filled one
+    #[default]
+    SelfOnly,
+    /// Also look at the cell in front of the ant (in its current direction, wrapping
+    /// around the edges): turn right when both cells are in the same state, left
+    /// otherwise. With an empty cell ahead this is the classic rule.
+    Ahead,
+}
+
+impl Vision {
+    fn from_looks_ahead(looks_ahead: bool) -> Self {
+        if looks_ahead {
+            Vision::Ahead
+        } else {
+            Vision::SelfOnly
+        }
+    }
+
+    fn looks_ahead(self) -> bool {
+        self == Vision::Ahead
+    }
+}
+
+impl GameConfig {
+    /// Current values of the params the simulation core depends on
+    pub fn core_params(&self) -> CoreParams {
+        let bg = self.common_cell_color.get();
+        CoreParams {
+            num_ants: self.num_ants.get(),
+            start_x_rel: self.start_x_rel.get(),
+            start_y_rel: self.start_y_rel.get(),
+            ant_color_saturation: self.ant_color_saturation.get(),
+            ant_color_brightness: self.ant_color_brightness.get(),
+            seed: self.seed.get(),
+            vision: Vision::from_looks_ahead(self.looks_ahead.get()),
+            bg_color: Color::Rgb {
+                r: bg.r,
+                g: bg.g,
+                b: bg.b,
+            },
+            hue_cycle_speed: self.hue_cycle_speed.get(),
+            hue_cycle_trails: self.hue_cycle_trails.get(),
+        }
+    }
+
+    /// Everything needed to reproduce a run after `steps` steps, keyed like the url params
+    pub fn metadata(&self, steps: u64) -> Vec<(&'static str, String)> {
+        vec![
+            ("rule", RULE.to_owned()),
+            ("seed", self.seed.get().to_string()),
+            ("steps", steps.to_string()),
+            ("number_of_ants", self.num_ants.get().to_string()),
+            ("start_x", self.start_x_rel.get().to_string()),
+            ("start_y", self.start_y_rel.get().to_string()),
+            ("ant_looks_ahead", self.looks_ahead.get().to_string()),
+            ("cell_size", self.cell_size.get().to_string()),
+            ("cell_border_size", self.cell_border_size.get().to_string()),
+            (
+                "ant_color_saturation",
+                self.ant_color_saturation.get().to_string(),
+            ),
+            (
+                "ant_color_brightness",
+                self.ant_color_brightness.get().to_string(),
+            ),
+            ("common_cell_color", self.common_cell_color.get().to_hex()),
+            ("hue_cycle_speed", self.hue_cycle_speed.get().to_string()),
+            ("hue_cycle_trails", self.hue_cycle_trails.get().to_string()),
+        ]
+    }
+}
+
+/// Plain copy of the [`GameConfig`] values used by [`LangtonCore`], so that the core
+/// doesn't depend on the debug UI and can be moved to a worker.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoreParams {
+    pub num_ants: usize,
+    pub start_x_rel: f32,
+    pub start_y_rel: f32,
+    pub ant_color_saturation: f32,
+    pub ant_color_brightness: f32,
+    pub seed: u32,
+    pub vision: Vision,
+    /// color of a cell when no ant left its mark on it
+    pub bg_color: Color,
+    /// degrees added to the hue of every ant each frame
+    pub hue_cycle_speed: f32,
+    /// also recolor the cells already drawn when the hue cycles
+    pub hue_cycle_trails: bool,
+}
+
+/// A cell that changed color during a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellChange {
+    pub x: usize,
+    pub y: usize,
+    pub color: Color,
+}
+
+/// The Langton's ant simulation without rendering: moves the ants on the board and
+/// records the cells that changed, for a renderer to pick up.
+pub struct LangtonCore {
+    ants: Ants,
+    /// id of the ant that filled each cell or [`EMPTY`], indexed by `x * height + y`.
+    /// `u32` rather than `Option<usize>` keeps the board 4 times smaller, see the
+    /// `step_bench` example.
+    board: Vec<u32>,
+    /// in cells
     width: usize,
+    /// in cells
     height: usize,
+    /// in cells, visible part of the board, used to place new ants
+    screen_height: usize,
+    steps: u64,
+    changes: Vec<CellChange>,
+    /// in degrees, added to the base hue of every ant
+    hue_offset: f32,
 }
 
-struct Ant {
-    x: usize,
-    y: usize,
-    direction: Direction,
-    id: usize,
-    color: Color,
+/// Board cell no ant left its mark on
+const EMPTY: u32 = u32::MAX;
+
+/// Ants stored as parallel vectors (structure of arrays) so the step loop walks
+/// contiguous memory. An ant's id is its index.
+#[derive(Default)]
+struct Ants {
+    x: Vec<usize>,
+    y: Vec<usize>,
+    direction: Vec<Direction>,
+    /// base hue, before the hue cycle offset
+    hue: Vec<f32>,
+    color: Vec<Color>,
 }
 
-impl Game {
-    pub fn new(config: Rc<RefCell<GameConfig>>, width: usize, height: usize) -> Self {
+impl Ants {
+    fn len(&self) -> usize {
+        self.x.len()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.x.truncate(len);
+        self.y.truncate(len);
+        self.direction.truncate(len);
+        self.hue.truncate(len);
+        self.color.truncate(len);
+    }
+
+    fn push(&mut self, x: usize, y: usize, direction: Direction, hue: f32, color: Color) {
+        self.x.push(x);
+        self.y.push(y);
+        self.direction.push(direction);
+        self.hue.push(hue);
+        self.color.push(color);
+    }
+}
+
+impl LangtonCore {
+    pub fn new(width: usize, height: usize) -> Self {
         Self {
-            ants: vec![],
-            board: vec![None; width * height],
-            config,
+            ants: Ants::default(),
+            board: vec![EMPTY; width * height],
             width,
             height,
+            screen_height: height,
+            steps: 0,
+            changes: vec![],
+            hue_offset: 0.0,
         }
     }
 
-    pub fn preview(width: usize, height: usize) -> Self {
-        let mut debug_ui = DebugUI::headless();
-        let config = GameConfig::new(&mut debug_ui);
-        Self {
-            ants: vec![],
-            board: vec![None; width * height],
-            config: Rc::new(RefCell::new(config)),
-            width,
-            height,
+    pub fn set_screen_height(&mut self, screen_height: usize) {
+        self.screen_height = screen_height;
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Cells changed since the changes were last drained, in the order they changed
+    pub fn last_changes(&self) -> &[CellChange] {
+        &self.changes
+    }
+
+    /// [`Self::last_changes`] with only the last change of each cell, so at most
+    /// `width * height` of them
+    pub fn merged_changes(&self) -> Vec<CellChange> {
+        let mut seen = vec![false; self.board.len()];
+        let mut merged: Vec<CellChange> = self
+            .changes
+            .iter()
+            .rev()
+            .filter(|change| !std::mem::replace(&mut seen[change.x * self.height + change.y], true))
+            .copied()
+            .collect();
+        merged.reverse();
+        merged
+    }
+
+    pub fn drain_changes(&mut self) -> std::vec::Drain<'_, CellChange> {
+        self.changes.drain(..)
+    }
+
+    pub fn step(&mut self, params: &CoreParams) {
+        self.balance_ants(params);
+        self.steps += 1;
+        // (height, width) — indices are swapped when passing to board/move APIs
+        let canvas_size = (self.height, self.width);
+        assert!(canvas_size.0 > 0, "Can't draw on a canvas of height 0 !");
+        assert!(canvas_size.1 > 0, "Can't draw on a canvas of width 0 !");
+        let n = self.ants.len();
+        let xs = &mut self.ants.x[..n];
+        let ys = &mut self.ants.y[..n];
+        let directions = &mut self.ants.direction[..n];
+        let colors = &self.ants.color[..n];
+        self.changes.reserve(n);
+        for id in 0..n {
+            let (x, y, direction) = (xs[id], ys[id], directions[id]);
+            let cell = x * canvas_size.0 + y;
+            let current_cell_state = self.board[cell];
+            let turn_right = match params.vision {
+                Vision::SelfOnly => current_cell_state == EMPTY,
+                Vision::Ahead => {
+                    let (ahead_x, ahead_y) = forward(x, y, direction, canvas_size.1, canvas_size.0);
+                    let ahead_cell_state = self.board[ahead_x * canvas_size.0 + ahead_y];
+                    (current_cell_state == EMPTY) == (ahead_cell_state == EMPTY)
+                }
+            };
+            let direction = if turn_right {
+                direction.right()
+            } else {
+                direction.left()
+            };
+            let new_cell_color = if current_cell_state == EMPTY {
+                self.board[cell] = id as u32;
+                colors[id]
+            } else {
+                self.board[cell] = EMPTY;
+                params.bg_color
+            };
+            self.changes.push(CellChange {
+                x,
+                y,
+                color: new_cell_color,
+            });
+            directions[id] = direction;
+            (xs[id], ys[id]) = forward(x, y, direction, canvas_size.1, canvas_size.0);
+        }
+    }
+
+    pub fn resize(&mut self, new_width: usize, new_height: usize) {
+        self.width = new_width;
+        self.height = new_height;
+        self.board = vec![EMPTY; new_width * new_height];
+        self.changes.clear();
+        for x in &mut self.ants.x {
+            *x = (*x).min(new_width.saturating_sub(1));
+        }
+        for y in &mut self.ants.y {
+            *y = (*y).min(new_height.saturating_sub(1));
+        }
+    }
+
+    /// Empties the board and restarts the step count, the ants keep going from where they are
+    pub fn clear(&mut self) {
+        self.board.fill(EMPTY);
+        self.steps = 0;
+        self.changes.clear();
+    }
+
+    /// Rotate the hue of every ant by `params.hue_cycle_speed` degrees for each of the
+    /// `frames` rendered since the last call.
+    ///
+    /// Cells drawn from now on use the new colors. By default the cells already drawn
+    /// keep the color they were drawn with. With `params.hue_cycle_trails`, every cell
+    /// still marked by an ant is redrawn in that ant's new color instead (recorded as
+    /// changes), which also keeps those cells from fading out. That scans the whole
+    /// board and records up to `width * height` changes each time the hue moves.
+    pub fn advance_hue(&mut self, params: &CoreParams, frames: u32) {
+        let hue_offset =
+            (self.hue_offset + params.hue_cycle_speed * frames as f32).rem_euclid(360.0);
+        if hue_offset == self.hue_offset {
+            return;
+        }
+        self.hue_offset = hue_offset;
+        for (color, hue) in self.ants.color.iter_mut().zip(&self.ants.hue) {
+            *color = hue_to_rgb(
+                (hue + self.hue_offset) % 360.0,
+                params.ant_color_saturation,
+                params.ant_color_brightness,
+            );
+        }
+        if params.hue_cycle_trails {
+            for (cell, &id) in self.board.iter().enumerate() {
+                if let Some(&color) = self.ants.color.get(id as usize) {
+                    self.changes.push(CellChange {
+                        x: cell / self.height,
+                        y: cell % self.height,
+                        color,
+                    });
+                }
+            }
         }
     }
 
-    fn balance_ants(&mut self, canvas: &Canvas) {
-        let num_ants = self.config.borrow().num_ants.get();
+    fn balance_ants(&mut self, params: &CoreParams) {
+        let num_ants = params.num_ants;
         match num_ants.cmp(&self.ants.len()) {
             std::cmp::Ordering::Less => self.ants.truncate(num_ants),
             std::cmp::Ordering::Greater => {
                 for i in self.ants.len()..num_ants {
-                    self.add_ant(i, canvas);
+                    self.add_ant(i, params);
                 }
             }
             std::cmp::Ordering::Equal => (),
         }
     }
 
-    fn add_ant(&mut self, id: usize, canvas: &Canvas) {
-        let config = self.config.borrow();
-        let num_ants = config.num_ants.get();
-        let seed = config.seed.get();
-        let seed_offset = (seed as f32 * 137.508) % 360.0;
+    fn add_ant(&mut self, id: usize, params: &CoreParams) {
+        let num_ants = params.num_ants;
+        let seed_offset = (params.seed as f32 * 137.508) % 360.0;
         let hue = if num_ants > 0 {
             (id as f32 * 360.0 / num_ants as f32 + seed_offset) % 360.0
         } else {
             0.0
         };
         let color = hue_to_rgb(
-            hue,
-            config.ant_color_saturation.get(),
-            config.ant_color_brightness.get(),
+            (hue + self.hue_offset) % 360.0,
+            params.ant_color_saturation,
+            params.ant_color_brightness,
         );
-        let width = canvas.width();
-        let screen_height = canvas.screen_height();
-        let ant = Ant {
-            x: ((width - 1) as f32 * config.start_x_rel.get()) as usize,
-            y: ((screen_height - 1) as f32 * config.start_y_rel.get()) as usize,
-            direction: Direction::default(),
-            id,
+        self.ants.push(
+            ((self.width - 1) as f32 * params.start_x_rel) as usize,
+            ((self.screen_height - 1) as f32 * params.start_y_rel) as usize,
+            Direction::default(),
+            hue,
             color,
-        };
-        self.ants.push(ant);
+        );
+    }
+}
+
+/// Renders a [`LangtonCore`] on the canvas of the main thread
+pub struct Game {
+    core: LangtonCore,
+    /// refreshed from `config` once per frame, see [`Simulation::end_frame`]
+    params: CoreParams,
+    config: Rc<RefCell<GameConfig>>,
+    /// set by the export button, see [`Game::export_with_metadata`]
+    export_requested: Rc<RefCell<bool>>,
+}
+
+impl Game {
+    pub fn new(config: Rc<RefCell<GameConfig>>, width: usize, height: usize) -> Self {
+        let params = config.borrow().core_params();
+        Self {
+            core: LangtonCore::new(width, height),
+            params,
+            config,
+            export_requested: Rc::new(RefCell::new(false)),
+        }
+    }
+
+    /// Export the canvas and the run metadata at the end of the frame where `requested`
+    /// gets set, e.g. by [`DebugUI::button`]
+    pub fn with_export_request(mut self, requested: Rc<RefCell<bool>>) -> Self {
+        self.export_requested = requested;
+        self
+    }
+
+    pub fn preview(width: usize, height: usize) -> Self {
+        let mut debug_ui = DebugUI::headless();
+        let config = GameConfig::new(&mut debug_ui);
+        Self::new(Rc::new(RefCell::new(config)), width, height)
+    }
+
+    /// Everything needed to reproduce the current run, see [`GameConfig::metadata`]
+    pub fn metadata(&self) -> Vec<(&'static str, String)> {
+        self.config.borrow().metadata(self.core.steps())
+    }
+
+    /// Screenshot of `canvas` as a PNG with the run metadata embedded as `tEXt` chunks,
+    /// along with the same metadata as a sidecar JSON.
+    #[cfg(target_arch = "wasm32")]
+    pub fn export_with_metadata(&self, canvas: &Canvas) -> (Blob, String) {
+        export_with_metadata(canvas, &self.metadata())
     }
 }
 
 impl Simulation for Game {
     fn step(&mut self, canvas: &mut Canvas) {
-        self.balance_ants(canvas);
-        let config = self.config.borrow();
-        // (height, width) — indices are swapped when passing to board/move APIs
-        let canvas_size = (self.height, self.width);
-        assert!(canvas_size.0 > 0, "Can't draw on a canvas of height 0 !");
-        assert!(canvas_size.1 > 0, "Can't draw on a canvas of width 0 !");
-        for ant in &mut self.ants {
-            let current_cell_state = self.board[ant.x * canvas_size.0 + ant.y];
-            let new_cell_color = match current_cell_state {
-                None => {
-                    ant.direction = ant.direction.right();
-                    self.board[ant.x * canvas_size.0 + ant.y] = Some(ant.id);
-                    ant.color
-                }
-                Some(_) => {
-                    ant.direction = ant.direction.left();
-                    self.board[ant.x * canvas_size.0 + ant.y] = None;
-                    let bg = config.common_cell_color.get();
-                    Color::Rgb {
-                        r: bg.r,
-                        g: bg.g,
-                        b: bg.b,
-                    }
-                }
-            };
-            canvas.fill_rect(ant.x, ant.y, new_cell_color);
-            ant.move_forward(canvas_size.1, canvas_size.0);
+        self.core.set_screen_height(canvas.screen_height());
+        self.core.step(&self.params);
+        for change in self.core.drain_changes() {
+            canvas.fill_rect(change.x, change.y, change.color);
         }
     }
 
-    fn on_canvas_resize(&mut self, new_width: usize, new_height: usize) {
-        self.width = new_width;
-        self.height = new_height;
-        self.board = vec![None; new_width * new_height];
-        for ant in &mut self.ants {
-            ant.x = ant.x.min(new_width.saturating_sub(1));
-            ant.y = ant.y.min(new_height.saturating_sub(1));
+    fn end_frame(&mut self, canvas: &mut Canvas) {
+        self.params = self.config.borrow().core_params();
+        self.core.advance_hue(&self.params, 1);
+        for change in self.core.drain_changes() {
+            canvas.fill_rect(change.x, change.y, change.color);
+        }
+        if self.export_requested.replace(false) {
+            // the screenshot only contains what was flushed
+            canvas.flush();
+            #[cfg(target_arch = "wasm32")]
+            download_export(self.export_with_metadata(canvas));
         }
     }
 
+    fn on_canvas_resize(&mut self, new_width: usize, new_height: usize) {
+        self.core.resize(new_width, new_height);
+    }
+
     fn on_clear(&mut self, canvas: &mut Canvas) {
         canvas.clear(self.bg_color());
-        self.board.fill(None);
+        self.core.clear();
     }
 
     fn bg_color(&self) -> Color {
@@ -272,36 +587,333 @@ impl Simulation for Game {
     }
 }
 
-impl Ant {
-    fn move_forward(&mut self, board_width: usize, board_height: usize) {
-        match self.direction {
-            Direction::North => {
-                if self.y < board_height - 1 {
-                    self.y += 1
-                } else {
-                    self.y = 0
-                }
+/// `metadata` as a JSON object, finite numbers and booleans are written unquoted
+pub fn metadata_json(metadata: &[(&'static str, String)]) -> String {
+    let fields: Vec<String> = metadata
+        .iter()
+        .map(|(key, value)| {
+            if value.parse::<f64>().is_ok_and(f64::is_finite) || value.parse::<bool>().is_ok() {
+                format!(r#""{key}":{value}"#)
+            } else {
+                let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+                format!(r#""{key}":"{value}""#)
             }
-            Direction::Est => {
-                if self.x < board_width - 1 {
-                    self.x += 1
-                } else {
-                    self.x = 0
-                }
+        })
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Screenshot of `canvas` as a PNG with `metadata` embedded as `tEXt` chunks, along
+/// with the same metadata as a sidecar JSON. Only what was flushed is in the screenshot.
+#[cfg(target_arch = "wasm32")]
+pub fn export_with_metadata(
+    canvas: &Canvas,
+    metadata: &[(&'static str, String)],
+) -> (Blob, String) {
+    let text: Vec<(&str, &str)> = metadata
+        .iter()
+        .map(|(key, value)| (*key, value.as_str()))
+        .collect();
+    (canvas.export_png(&text), metadata_json(metadata))
+}
+
+/// Downloads an export as `langton-<date>.png` and `langton-<date>.json`, see
+/// [`Game::export_with_metadata`]. Video recordings (shift+R) are plain webm files and
+/// carry no metadata.
+#[cfg(target_arch = "wasm32")]
+pub fn download_export((png, json): (Blob, String)) {
+    let parts = js_sys::Array::new();
+    parts.push(&json.into());
+    let options = BlobPropertyBag::new();
+    options.set_type("application/json");
+    let json = Blob::new_with_str_sequence_and_options(&parts, &options).unwrap();
+
+    let now = js_sys::Date::new_0().to_iso_string();
+    common::download_blob(&png, &format!("langton-{now}.png"));
+    common::download_blob(&json, &format!("langton-{now}.json"));
+}
+
+/// Messages sent to a simulation worker, flattened to a `f64` array to go through
+/// `postMessage`. The worker answers a [`WorkerRequest::Step`] with the changed cells,
+/// see [`encode_changes`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorkerRequest {
+    /// Params to use for the next steps, must be sent before the first step
+    Configure(CoreParams),
+    Resize {
+        width: usize,
+        height: usize,
+        screen_height: usize,
+    },
+    /// Run `steps` steps, advance the hue cycle by `frames` frames and send back the
+    /// cells that changed
+    Step {
+        steps: u32,
+        frames: u32,
+    },
+    Clear,
+}
+
+impl WorkerRequest {
+    pub fn encode(&self) -> Vec<f64> {
+        match self {
+            WorkerRequest::Configure(params) => vec![
+                0.0,
+                params.num_ants as f64,
+                params.start_x_rel as f64,
+                params.start_y_rel as f64,
+                params.ant_color_saturation as f64,
+                params.ant_color_brightness as f64,
+                params.seed as f64,
+                params.vision.looks_ahead() as u8 as f64,
+                pack_color(params.bg_color) as f64,
+                params.hue_cycle_speed as f64,
+                params.hue_cycle_trails as u8 as f64,
+            ],
+            WorkerRequest::Resize {
+                width,
+                height,
+                screen_height,
+            } => vec![1.0, *width as f64, *height as f64, *screen_height as f64],
+            WorkerRequest::Step { steps, frames } => vec![2.0, *steps as f64, *frames as f64],
+            WorkerRequest::Clear => vec![3.0],
+        }
+    }
+
+    pub fn decode(data: &[f64]) -> Option<Self> {
+        let request = match *data {
+            [
+                0.0,
+                num_ants,
+                start_x,
+                start_y,
+                saturation,
+                brightness,
+                seed,
+                vision,
+                bg,
+                hue_cycle_speed,
+                hue_cycle_trails,
+            ] => WorkerRequest::Configure(CoreParams {
+                num_ants: num_ants as usize,
+                start_x_rel: start_x as f32,
+                start_y_rel: start_y as f32,
+                ant_color_saturation: saturation as f32,
+                ant_color_brightness: brightness as f32,
+                seed: seed as u32,
+                vision: Vision::from_looks_ahead(vision != 0.0),
+                bg_color: unpack_color(bg as u32),
+                hue_cycle_speed: hue_cycle_speed as f32,
+                hue_cycle_trails: hue_cycle_trails != 0.0,
+            }),
+            [1.0, width, height, screen_height] => WorkerRequest::Resize {
+                width: width as usize,
+                height: height as usize,
+                screen_height: screen_height as usize,
+            },
+            [2.0, steps, frames] => WorkerRequest::Step {
+                steps: steps as u32,
+                frames: frames as u32,
+            },
+            [3.0] => WorkerRequest::Clear,
+            _ => return None,
+        };
+        Some(request)
+    }
+}
+
+/// Worker side of the protocol: owns the core and applies the requests
+pub struct WorkerSim {
+    core: LangtonCore,
+    params: Option<CoreParams>,
+}
+
+impl Default for WorkerSim {
+    fn default() -> Self {
+        Self {
+            core: LangtonCore::new(1, 1),
+            params: None,
+        }
+    }
+}
+
+impl WorkerSim {
+    /// Returns the encoded changed cells for [`WorkerRequest::Step`], `None` otherwise
+    pub fn handle(&mut self, request: WorkerRequest) -> Option<Vec<u32>> {
+        match request {
+            WorkerRequest::Configure(params) => self.params = Some(params),
+            WorkerRequest::Resize {
+                width,
+                height,
+                screen_height,
+            } => {
+                self.core.resize(width, height);
+                self.core.set_screen_height(screen_height);
             }
-            Direction::South => {
-                if self.y > 0 {
-                    self.y -= 1
-                } else {
-                    self.y = board_height - 1
+            WorkerRequest::Step { steps, frames } => {
+                if let Some(params) = &self.params {
+                    for _ in 0..steps {
+                        self.core.step(params);
+                    }
+                    self.core.advance_hue(params, frames);
                 }
+                let changes = encode_changes(&self.core.merged_changes());
+                self.core.drain_changes();
+                return Some(changes);
             }
-            Direction::West => {
-                if self.x > 0 {
-                    self.x -= 1
-                } else {
-                    self.x = board_width - 1
-                }
+            WorkerRequest::Clear => self.core.clear(),
+        }
+        None
+    }
+}
+
+/// Main thread side of [`WorkerRequest::Step`]: queues the steps asked by the runner
+/// and the frames rendered while a request is in flight, so that they are sent with
+/// the next one instead of being lost. When the worker can't keep up, steps beyond
+/// [`MAX_BACKLOG_FRAMES`] frames' worth are dropped.
+#[derive(Debug, Default)]
+pub struct StepBatcher {
+    pending: u32,
+    /// steps queued since the last [`StepBatcher::end_frame`]
+    frame_steps: u32,
+    /// frames rendered since the last request, for the hue cycle
+    pending_frames: u32,
+    in_flight: Option<u32>,
+    /// set by a clear or a resize while a step is in flight, its (stale) changes are
+    /// then ignored
+    discard_in_flight: bool,
+    done: u64,
+}
+
+/// Frames' worth of steps a [`StepBatcher`] keeps queued
+pub const MAX_BACKLOG_FRAMES: u32 = 4;
+
+impl StepBatcher {
+    pub fn queue(&mut self, steps: u32) {
+        self.pending = self.pending.saturating_add(steps);
+        self.frame_steps = self.frame_steps.saturating_add(steps);
+    }
+
+    pub fn end_frame(&mut self) {
+        let max_pending = std::mem::take(&mut self.frame_steps).saturating_mul(MAX_BACKLOG_FRAMES);
+        self.pending = self.pending.min(max_pending);
+        self.pending_frames = self.pending_frames.saturating_add(1);
+    }
+
+    /// [`WorkerRequest::Step`] to send, `None` while a request is in flight or nothing
+    /// is queued
+    pub fn take_batch(&mut self) -> Option<WorkerRequest> {
+        if self.in_flight.is_some() || (self.pending == 0 && self.pending_frames == 0) {
+            return None;
+        }
+        let steps = std::mem::take(&mut self.pending);
+        self.in_flight = Some(steps);
+        Some(WorkerRequest::Step {
+            steps,
+            frames: std::mem::take(&mut self.pending_frames),
+        })
+    }
+
+    /// The worker answered the request in flight, returns whether its changes are to be drawn
+    pub fn complete(&mut self) -> bool {
+        let steps = self.in_flight.take().unwrap_or(0);
+        if std::mem::take(&mut self.discard_in_flight) {
+            return false;
+        }
+        self.done += steps as u64;
+        true
+    }
+
+    /// Forgets the queued steps, the request in flight ran on the board before the clear
+    pub fn clear(&mut self) {
+        self.pending = 0;
+        self.done = 0;
+        self.discard_in_flight = self.in_flight.is_some();
+    }
+
+    /// The board is resized, the changes of the request in flight are for the old one
+    pub fn resize(&mut self) {
+        self.discard_in_flight = self.in_flight.is_some();
+    }
+
+    /// Steps run by the worker whose changes were received since the last clear, like
+    /// [`LangtonCore::steps`]
+    pub fn steps_done(&self) -> u64 {
+        self.done
+    }
+}
+
+/// Flattens changes to `[x, y, rgba, x, y, rgba, ...]`
+pub fn encode_changes(changes: &[CellChange]) -> Vec<u32> {
+    changes
+        .iter()
+        .flat_map(|change| [change.x as u32, change.y as u32, pack_color(change.color)])
+        .collect()
+}
+
+pub fn decode_changes(data: &[u32]) -> Vec<CellChange> {
+    data.chunks_exact(3)
+        .map(|chunk| CellChange {
+            x: chunk[0] as usize,
+            y: chunk[1] as usize,
+            color: unpack_color(chunk[2]),
+        })
+        .collect()
+}
+
+fn pack_color(color: Color) -> u32 {
+    let (r, g, b, a) = match color {
+        Color::Rgb { r, g, b } => (r, g, b, 255),
+        Color::Rgba { r, g, b, a } => (r, g, b, a),
+        Color::Named(NamedColor::White) => (255, 255, 255, 255),
+        Color::Named(NamedColor::Black) => (0, 0, 0, 255),
+    };
+    u32::from_be_bytes([r, g, b, a])
+}
+
+/// Opaque colors come back as [`Color::Rgb`]
+fn unpack_color(packed: u32) -> Color {
+    match packed.to_be_bytes() {
+        [r, g, b, 255] => Color::Rgb { r, g, b },
+        [r, g, b, a] => Color::Rgba { r, g, b, a },
+    }
+}
+
+/// Cell next to `(x, y)` in `direction`, wrapping around the board edges
+fn forward(
+    x: usize,
+    y: usize,
+    direction: Direction,
+    board_width: usize,
+    board_height: usize,
+) -> (usize, usize) {
+    match direction {
+        Direction::North => {
+            if y < board_height - 1 {
+                (x, y + 1)
+            } else {
+                (x, 0)
+            }
+        }
+        Direction::Est => {
+            if x < board_width - 1 {
+                (x + 1, y)
+            } else {
+                (0, y)
+            }
+        }
+        Direction::South => {
+            if y > 0 {
+                (x, y - 1)
+            } else {
+                (x, board_height - 1)
+            }
+        }
+        Direction::West => {
+            if x > 0 {
+                (x - 1, y)
+            } else {
+                (board_width - 1, y)
             }
         }
     }
@@ -338,3 +950,592 @@ fn hue_to_rgb(hue: f32, saturation: f32, lightness: f32) -> Color {
 
     Color::Rgb { r, g, b }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CellChange, CoreParams, Direction, EMPTY, LangtonCore, MAX_BACKLOG_FRAMES, StepBatcher,
+        Vision, WorkerRequest, WorkerSim, decode_changes, encode_changes, forward, metadata_json,
+    };
+    use canvas::Color;
+    use rstest::rstest;
+
+    fn params() -> CoreParams {
+        CoreParams {
+            num_ants: 3,
+            start_x_rel: 0.5,
+            start_y_rel: 0.25,
+            ant_color_saturation: 0.3,
+            ant_color_brightness: 0.7,
+            seed: 4_000_000_000,
+            vision: Vision::Ahead,
+            bg_color: Color::Rgb {
+                r: 30,
+                g: 30,
+                b: 30,
+            },
+            hue_cycle_speed: 0.0,
+            hue_cycle_trails: false,
+        }
+    }
+
+    fn cycling_params(trails: bool) -> CoreParams {
+        CoreParams {
+            hue_cycle_speed: 90.0,
+            hue_cycle_trails: trails,
+            ..params()
+        }
+    }
+
+    #[rstest]
+    #[case(2, 2, Direction::North, (2, 3))]
+    #[case(2, 2, Direction::Est, (3, 2))]
+    #[case(2, 2, Direction::South, (2, 1))]
+    #[case(2, 2, Direction::West, (1, 2))]
+    #[case(1, 4, Direction::North, (1, 0))]
+    #[case(4, 1, Direction::Est, (0, 1))]
+    #[case(1, 0, Direction::South, (1, 4))]
+    #[case(0, 1, Direction::West, (4, 1))]
+    fn test_forward_wraps(
+        #[case] x: usize,
+        #[case] y: usize,
+        #[case] direction: Direction,
+        #[case] expected: (usize, usize),
+    ) {
+        assert_eq!(forward(x, y, direction, 5, 5), expected);
+    }
+
+    #[rstest]
+    #[case(false, Vision::SelfOnly)]
+    #[case(true, Vision::Ahead)]
+    fn test_vision_from_looks_ahead(#[case] looks_ahead: bool, #[case] expected: Vision) {
+        assert_eq!(Vision::from_looks_ahead(looks_ahead), expected);
+        assert_eq!(expected.looks_ahead(), looks_ahead);
+    }
+
+    #[rstest]
+    #[case(WorkerRequest::Configure(params()))]
+    #[case(WorkerRequest::Configure(cycling_params(true)))]
+    #[case(WorkerRequest::Resize { width: 1920, height: 1080, screen_height: 900 })]
+    #[case(WorkerRequest::Step { steps: 123_456, frames: 3 })]
+    #[case(WorkerRequest::Clear)]
+    fn test_worker_request_roundtrip(#[case] request: WorkerRequest) {
+        assert_eq!(WorkerRequest::decode(&request.encode()), Some(request));
+    }
+
+    #[rstest]
+    #[case(&[])]
+    #[case(&[42.0])]
+    #[case(&[2.0])]
+    #[case(&[2.0, 1.0])]
+    #[case(&[3.0, 1.0])]
+    fn test_worker_request_decode_invalid(#[case] data: &[f64]) {
+        assert_eq!(WorkerRequest::decode(data), None);
+    }
+
+    #[test]
+    fn test_changes_roundtrip() {
+        let changes = vec![
+            CellChange {
+                x: 0,
+                y: 7,
+                color: Color::Rgb { r: 1, g: 2, b: 3 },
+            },
+            CellChange {
+                x: 4000,
+                y: 2,
+                color: Color::Rgba {
+                    r: 250,
+                    g: 0,
+                    b: 128,
+                    a: 10,
+                },
+            },
+        ];
+        assert_eq!(decode_changes(&encode_changes(&changes)), changes);
+    }
+
+    #[test]
+    fn test_worker_sim_matches_core() {
+        let params = params();
+        let mut core = LangtonCore::new(20, 10);
+        let mut expected = vec![];
+        for steps in [1, 9, 40] {
+            for _ in 0..steps {
+                core.step(&params);
+            }
+            expected.extend(core.merged_changes());
+            core.drain_changes();
+        }
+
+        let mut worker = WorkerSim::default();
+        assert_eq!(
+            worker.handle(WorkerRequest::Resize {
+                width: 20,
+                height: 10,
+                screen_height: 10
+            }),
+            None
+        );
+        assert_eq!(worker.handle(WorkerRequest::Configure(params)), None);
+        let mut got = vec![];
+        for steps in [1, 9, 40] {
+            got.extend(decode_changes(
+                &worker
+                    .handle(WorkerRequest::Step { steps, frames: 1 })
+                    .unwrap(),
+            ));
+        }
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn test_metadata_json_quotes_non_finite_numbers() {
+        let metadata = [
+            ("rule", "RL".to_owned()),
+            ("steps", "42".to_owned()),
+            ("start_x", "0.5".to_owned()),
+            ("hue_cycle_trails", "true".to_owned()),
+            ("nan", "NaN".to_owned()),
+            ("inf", "inf".to_owned()),
+            ("neg_inf", "-inf".to_owned()),
+            ("quoted", r#"a"b\c"#.to_owned()),
+        ];
+        assert_eq!(
+            metadata_json(&metadata),
+            r#"{"rule":"RL","steps":42,"start_x":0.5,"hue_cycle_trails":true,"nan":"NaN","inf":"inf","neg_inf":"-inf","quoted":"a\"b\\c"}"#
+        );
+    }
+
+    fn step(steps: u32, frames: u32) -> Option<WorkerRequest> {
+        Some(WorkerRequest::Step { steps, frames })
+    }
+
+    #[test]
+    fn test_step_batcher_keeps_steps_queued_while_in_flight() {
+        let mut batcher = StepBatcher::default();
+        assert_eq!(batcher.take_batch(), None);
+
+        batcher.queue(3);
+        batcher.end_frame();
+        assert_eq!(batcher.take_batch(), step(3, 1));
+        // frames rendered while the worker is busy
+        batcher.queue(2);
+        batcher.end_frame();
+        assert_eq!(batcher.take_batch(), None);
+        batcher.queue(5);
+        batcher.end_frame();
+        assert_eq!(batcher.take_batch(), None);
+
+        assert!(batcher.complete());
+        assert_eq!(batcher.steps_done(), 3);
+        assert_eq!(batcher.take_batch(), step(7, 2));
+        assert!(batcher.complete());
+        assert_eq!(batcher.steps_done(), 10);
+        assert_eq!(batcher.take_batch(), None);
+    }
+
+    #[test]
+    fn test_step_batcher_caps_the_backlog() {
+        let mut batcher = StepBatcher::default();
+        batcher.queue(10);
+        batcher.end_frame();
+        assert_eq!(batcher.take_batch(), step(10, 1));
+        // the worker is stuck on the first request
+        for _ in 0..100 {
+            batcher.queue(10);
+            batcher.end_frame();
+        }
+        assert!(batcher.complete());
+        assert_eq!(batcher.take_batch(), step(10 * MAX_BACKLOG_FRAMES, 100));
+
+        batcher.queue(u32::MAX);
+        batcher.queue(u32::MAX);
+        batcher.end_frame();
+        assert!(batcher.complete());
+        assert_eq!(batcher.take_batch(), step(u32::MAX, 1));
+    }
+
+    #[test]
+    fn test_step_batcher_sends_frames_without_steps() {
+        let mut batcher = StepBatcher::default();
+        batcher.end_frame();
+        assert_eq!(batcher.take_batch(), step(0, 1));
+        assert!(batcher.complete());
+        assert_eq!(batcher.steps_done(), 0);
+    }
+
+    #[test]
+    fn test_step_batcher_clear_discards_the_request_in_flight() {
+        let mut batcher = StepBatcher::default();
+        batcher.queue(4);
+        assert_eq!(batcher.take_batch(), step(4, 0));
+        batcher.queue(2);
+        batcher.clear();
+
+        assert_eq!(batcher.take_batch(), None);
+        assert!(!batcher.complete());
+        assert_eq!(batcher.steps_done(), 0);
+        batcher.queue(1);
+        assert_eq!(batcher.take_batch(), step(1, 0));
+        assert!(batcher.complete());
+        assert_eq!(batcher.steps_done(), 1);
+    }
+
+    #[test]
+    fn test_step_batcher_resize_discards_the_request_in_flight() {
+        let mut batcher = StepBatcher::default();
+        batcher.queue(4);
+        assert_eq!(batcher.take_batch(), step(4, 0));
+        batcher.queue(2);
+        batcher.resize();
+
+        assert_eq!(batcher.take_batch(), None);
+        assert!(!batcher.complete());
+        assert_eq!(batcher.steps_done(), 0);
+        // the queued steps run on the resized board
+        assert_eq!(batcher.take_batch(), step(2, 0));
+        assert!(batcher.complete());
+        assert_eq!(batcher.steps_done(), 2);
+    }
+
+    #[test]
+    fn test_worker_sim_needs_configure_before_stepping() {
+        let mut worker = WorkerSim::default();
+        assert_eq!(
+            worker.handle(WorkerRequest::Step {
+                steps: 10,
+                frames: 1
+            }),
+            Some(vec![])
+        );
+    }
+
+    #[test]
+    fn test_advance_hue_recolors_new_cells_only() {
+        let params = cycling_params(false);
+        let mut core = LangtonCore::new(20, 10);
+        core.step(&params);
+        let before = core.drain_changes().next().unwrap();
+        core.advance_hue(&params, 1);
+        assert_eq!(core.last_changes(), &[]);
+        core.clear();
+        core.step(&params);
+        let after = core.drain_changes().next().unwrap();
+        assert_ne!(after.color, before.color);
+    }
+
+    #[test]
+    fn test_advance_hue_recolors_trails() {
+        let params = CoreParams {
+            num_ants: 1,
+            ..cycling_params(true)
+        };
+        let mut core = LangtonCore::new(20, 10);
+        for _ in 0..4 {
+            core.step(&params);
+        }
+        let drawn: Vec<_> = core.drain_changes().collect();
+        core.advance_hue(&params, 1);
+        let redrawn = core.last_changes();
+        assert_eq!(redrawn.len(), drawn.len());
+        for cell in redrawn {
+            let old = drawn
+                .iter()
+                .find(|c| (c.x, c.y) == (cell.x, cell.y))
+                .unwrap();
+            assert_ne!(cell.color, old.color);
+        }
+    }
+
+    #[test]
+    fn test_advance_hue_by_frames() {
+        let params = cycling_params(false);
+        let mut one_by_one = LangtonCore::new(20, 10);
+        let mut at_once = LangtonCore::new(20, 10);
+        one_by_one.step(&params);
+        at_once.step(&params);
+        for _ in 0..3 {
+            one_by_one.advance_hue(&params, 1);
+        }
+        at_once.advance_hue(&params, 3);
+        assert_eq!(one_by_one.ants.color, at_once.ants.color);
+    }
+
+    #[test]
+    fn test_advance_hue_skips_trails_when_the_hue_does_not_move() {
+        let params = cycling_params(true);
+        let mut core = LangtonCore::new(20, 10);
+        core.step(&params);
+        core.drain_changes();
+        core.advance_hue(&params, 0);
+        assert_eq!(core.last_changes(), &[]);
+        core.advance_hue(
+            &CoreParams {
+                hue_cycle_speed: 0.0,
+                ..params
+            },
+            1,
+        );
+        assert_eq!(core.last_changes(), &[]);
+    }
+
+    #[test]
+    fn test_advance_hue_full_turn() {
+        let params = cycling_params(false);
+        let mut core = LangtonCore::new(20, 10);
+        core.step(&params);
+        let before = core.drain_changes().next().unwrap();
+        for _ in 0..4 {
+            core.advance_hue(&params, 1);
+        }
+        core.clear();
+        core.step(&params);
+        assert_eq!(core.drain_changes().next().unwrap().color, before.color);
+    }
+
+    /// Classic RL rule, ants start in the middle of an 11x11 board at (5, 5)
+    fn classic_params(num_ants: usize) -> CoreParams {
+        CoreParams {
+            num_ants,
+            start_x_rel: 0.5,
+            start_y_rel: 0.5,
+            vision: Vision::SelfOnly,
+            ..params()
+        }
+    }
+
+    fn ant(core: &LangtonCore, id: usize) -> (usize, usize, Direction) {
+        (core.ants.x[id], core.ants.y[id], core.ants.direction[id])
+    }
+
+    /// Filled cells, sorted
+    fn filled(core: &LangtonCore) -> Vec<(usize, usize)> {
+        (0..core.width)
+            .flat_map(|x| (0..core.height).map(move |y| (x, y)))
+            .filter(|(x, y)| core.board[x * core.height + y] != EMPTY)
+            .collect()
+    }
+
+    // North is +y, right turns go North -> Est -> South -> West
+    #[rstest]
+    #[case(0, (5, 5, Direction::North), &[])]
+    #[case(1, (6, 5, Direction::Est), &[(5, 5)])]
+    #[case(4, (5, 5, Direction::North), &[(5, 4), (5, 5), (6, 4), (6, 5)])]
+    #[case(5, (4, 5, Direction::West), &[(5, 4), (6, 4), (6, 5)])]
+    #[case(8, (5, 5, Direction::South), &[(4, 5), (4, 6), (5, 4), (5, 6), (6, 4), (6, 5)])]
+    #[case(10, (4, 4, Direction::South), &[(4, 6), (5, 4), (5, 5), (5, 6), (6, 4), (6, 5)])]
+    fn test_classic_rule_first_steps(
+        #[case] steps: usize,
+        #[case] expected_ant: (usize, usize, Direction),
+        #[case] expected_filled: &[(usize, usize)],
+    ) {
+        let params = classic_params(1);
+        let mut core = LangtonCore::new(11, 11);
+        core.balance_ants(&params);
+        for _ in 0..steps {
+            core.step(&params);
+        }
+        assert_eq!(ant(&core, 0), expected_ant);
+        assert_eq!(filled(&core), expected_filled);
+        assert_eq!(core.steps(), steps as u64);
+    }
+
+    #[test]
+    fn test_clear_restarts_the_step_count() {
+        let params = classic_params(1);
+        let mut core = LangtonCore::new(11, 11);
+        for _ in 0..5 {
+            core.step(&params);
+        }
+        core.clear();
+        assert_eq!(core.steps(), 0);
+        assert_eq!(filled(&core), vec![]);
+        core.step(&params);
+        assert_eq!(core.steps(), 1);
+    }
+
+    #[test]
+    fn test_classic_rule_changes() {
+        let params = classic_params(1);
+        let mut core = LangtonCore::new(11, 11);
+        for _ in 0..5 {
+            core.step(&params);
+        }
+        let ant_color = core.ants.color[0];
+        let changes: Vec<_> = core
+            .drain_changes()
+            .map(|change| (change.x, change.y, change.color))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (5, 5, ant_color),
+                (6, 5, ant_color),
+                (6, 4, ant_color),
+                (5, 4, ant_color),
+                (5, 5, params.bg_color),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merged_changes_keep_the_last_change_of_each_cell() {
+        let params = classic_params(1);
+        let mut core = LangtonCore::new(11, 11);
+        for _ in 0..5 {
+            core.step(&params);
+        }
+        let ant_color = core.ants.color[0];
+        let merged: Vec<_> = core
+            .merged_changes()
+            .into_iter()
+            .map(|change| (change.x, change.y, change.color))
+            .collect();
+        assert_eq!(
+            merged,
+            vec![
+                (6, 5, ant_color),
+                (6, 4, ant_color),
+                (5, 4, ant_color),
+                (5, 5, params.bg_color),
+            ]
+        );
+    }
+
+    /// Ants move one after the other in id order, each one seeing the cells flipped by
+    /// the ants before it in the same step
+    #[test]
+    fn test_ants_on_the_same_cell_move_in_id_order() {
+        let params = classic_params(2);
+        let mut core = LangtonCore::new(11, 11);
+        core.step(&params);
+        // ant 0 finds the cell empty: turns right and fills it
+        assert_eq!(ant(&core, 0), (6, 5, Direction::Est));
+        // ant 1 finds it filled by ant 0: turns left and empties it
+        assert_eq!(ant(&core, 1), (4, 5, Direction::West));
+        assert_eq!(filled(&core), vec![]);
+        let changes: Vec<_> = core.drain_changes().collect();
+        assert_eq!(changes.len(), 2);
+        assert_ne!(changes[0].color, params.bg_color);
+        assert_eq!(changes[1].color, params.bg_color);
+    }
+
+    #[test]
+    fn test_multiple_ants_are_deterministic() {
+        let params = classic_params(7);
+        let run = || {
+            let mut core = LangtonCore::new(11, 11);
+            let mut changes = vec![];
+            for _ in 0..500 {
+                core.step(&params);
+                changes.extend(core.drain_changes());
+            }
+            (changes, filled(&core))
+        };
+        assert_eq!(run(), run());
+    }
+
+    /// Plain rendition of the multi-ant rule, one struct per ant and a map as the board,
+    /// for the optimized [`LangtonCore`] to match
+    fn reference_changes(
+        params: &CoreParams,
+        colors: &[Color],
+        (width, height): (usize, usize),
+        steps: usize,
+    ) -> Vec<CellChange> {
+        struct Ant {
+            x: usize,
+            y: usize,
+            direction: Direction,
+        }
+        let mut ants: Vec<Ant> = colors
+            .iter()
+            .map(|_| Ant {
+                x: ((width - 1) as f32 * params.start_x_rel) as usize,
+                y: ((height - 1) as f32 * params.start_y_rel) as usize,
+                direction: Direction::North,
+            })
+            .collect();
+        let mut filled = std::collections::HashMap::new();
+        let mut changes = vec![];
+        for _ in 0..steps {
+            for (id, ant) in ants.iter_mut().enumerate() {
+                let here = filled.contains_key(&(ant.x, ant.y));
+                let turn_right = match params.vision {
+                    Vision::SelfOnly => !here,
+                    Vision::Ahead => {
+                        let ahead = forward(ant.x, ant.y, ant.direction, width, height);
+                        here == filled.contains_key(&ahead)
+                    }
+                };
+                ant.direction = if turn_right {
+                    ant.direction.right()
+                } else {
+                    ant.direction.left()
+                };
+                let color = if here {
+                    filled.remove(&(ant.x, ant.y));
+                    params.bg_color
+                } else {
+                    filled.insert((ant.x, ant.y), id);
+                    colors[id]
+                };
+                changes.push(CellChange {
+                    x: ant.x,
+                    y: ant.y,
+                    color,
+                });
+                (ant.x, ant.y) = forward(ant.x, ant.y, ant.direction, width, height);
+            }
+        }
+        changes
+    }
+
+    #[rstest]
+    #[case(Vision::SelfOnly)]
+    #[case(Vision::Ahead)]
+    fn test_core_matches_the_reference_rule(#[case] vision: Vision) {
+        let params = CoreParams {
+            vision,
+            ..classic_params(50)
+        };
+        let mut core = LangtonCore::new(23, 17);
+        core.balance_ants(&params);
+        let mut changes = vec![];
+        for _ in 0..300 {
+            core.step(&params);
+            changes.extend(core.drain_changes());
+        }
+        let expected = reference_changes(&params, &core.ants.color, (23, 17), 300);
+        assert_eq!(changes, expected);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_game_draws_the_board_on_a_mock_canvas() {
+        use super::{Game, GameConfig};
+        use canvas::Canvas;
+        use debug_ui::DebugUI;
+        use engine::Simulation;
+        use std::{cell::RefCell, rc::Rc};
+
+        let config = GameConfig::new(&mut DebugUI::headless());
+        let mut game = Game::new(Rc::new(RefCell::new(config)), 20, 20);
+        let mut canvas = Canvas::mock(20, 20);
+        for _ in 0..50 {
+            game.step(&mut canvas);
+        }
+        game.end_frame(&mut canvas);
+        canvas.flush();
+
+        let bg = game.bg_color();
+        for x in 0..20 {
+            for y in 0..20 {
+                let drawn = canvas.flushed_cell(x, y).filter(|&color| color != bg);
+                let board = game.core.board[x * 20 + y];
+                assert_eq!(drawn.is_some(), board != EMPTY, "cell ({x}, {y})");
+            }
+        }
+    }
+}
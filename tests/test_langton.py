@@ -34,6 +34,14 @@ def set_param_value(page: Page, label_text: str, value: float | int) -> None:
     number_input.dispatch_event("change")
 
 
+def param_input_value(page: Page, label_text: str) -> float:
+    """Read the number input next to a parameter label."""
+    container = page.locator(
+        ".DebugUI-param-container", has=page.locator(f"text={label_text}")
+    )
+    return float(container.locator("input[type=number]").input_value())
+
+
 def mark_canvas(page: Page) -> None:
     """Tag the current canvas element so we can detect if it gets replaced."""
     page.evaluate("document.querySelector('canvas')._test_marker = true")
@@ -81,6 +89,20 @@ def test_page_loads(page: Page):
     )
 
 
+def test_worker_mode_animates(page: Page):
+    """With ?worker the simulation runs in a web worker and still draws on the canvas."""
+    load_and_wait(page, "&worker")
+    assert canvas_count(page) == 1
+    assert canvas_is_animating(page), (
+        "Canvas must be drawn from the worker's step replies"
+    )
+    errors = [
+        m for m in page._console_msgs  # type: ignore
+        if isinstance(m, str) or m.type == "error"
+    ]
+    assert not errors, f"Worker mode logged errors: {errors}"
+
+
 def test_debug_ui_visible(page: Page):
     """The debug UI panel renders when ?debug is in the URL."""
     load_and_wait(page)
@@ -175,6 +197,68 @@ def test_url_updated_on_param_change(page: Page):
     )
 
 
+def test_save_params_in_url_checkbox(page: Page):
+    """Unticking "save params in url" removes the params from the URL, ticking it brings them back."""
+    load_and_wait(page)
+    set_param_value(page, "alpha retention", 200)
+    checkbox = page.locator("#debugui-save-params-in-url")
+    expect(checkbox).to_be_checked()
+
+    checkbox.uncheck()
+    assert "alpha_retention" not in page.url, (
+        f"Expected alpha_retention to be removed from the URL, got: {page.url}"
+    )
+    assert "debug" in page.url, f"Expected debug to stay in the URL, got: {page.url}"
+
+    checkbox.check()
+    assert "alpha_retention=200" in page.url, (
+        f"Expected alpha_retention=200 back in the URL, got: {page.url}"
+    )
+
+
+def test_back_navigation_restores_param(page: Page):
+    """
+    Going back after a param change puts the previous value back in the input,
+    without the update writing a new history entry (the forward entry survives).
+    """
+    load_and_wait(page)
+    set_param_value(page, "alpha retention", 200)
+    length = page.evaluate("history.length")
+
+    page.go_back()
+    page.wait_for_timeout(300)
+    assert "alpha_retention=200" not in page.url
+    assert param_input_value(page, "alpha retention") == 251
+    assert page.evaluate("history.length") == length, (
+        "Applying the url after a back navigation must not push a history entry"
+    )
+    assert "alpha_retention" not in page.url, (
+        f"Applying the url must not write it back, got: {page.url}"
+    )
+
+    page.go_forward()
+    page.wait_for_timeout(300)
+    assert "alpha_retention=200" in page.url
+    assert param_input_value(page, "alpha retention") == 200
+
+
+def test_back_navigation_restarts_on_restart_param(page: Page):
+    """Going back over a change of a needs_restart param restarts the run."""
+    load_and_wait(page)
+    set_param_value(page, "start x", 0.3)
+    page.wait_for_timeout(500)
+    mark_canvas(page)
+
+    page.go_back()
+    page.wait_for_selector("canvas", timeout=10_000)
+    page.wait_for_timeout(500)
+    assert "start_x=0.3" not in page.url
+    assert param_input_value(page, "start x") == 0.8
+    assert canvas_is_fresh(page), "Expected the run to restart with the previous start x"
+    expect(page.locator("canvas")).to_have_count(1)
+    assert canvas_is_animating(page)
+
+
 # ---------------------------------------------------------------------------
 # Restart-param tests
 # ---------------------------------------------------------------------------